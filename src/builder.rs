@@ -0,0 +1,238 @@
+//! A builder for fluently configuring an **EnvelopeDetector**.
+//!
+//! The primary type of interest in this module is the
+//! [**EnvelopeDetectorBuilder**](./struct.EnvelopeDetectorBuilder).
+
+use {Ballistics, EnvelopeDetector, FullScale, Mode, Peak, Rms};
+use peak::FullWave;
+use sample::Frame;
+
+
+/// The attack time used by the builder when none is specified.
+pub const DEFAULT_ATTACK_FRAMES: f32 = 1.0;
+/// The release time used by the builder when none is specified.
+pub const DEFAULT_RELEASE_FRAMES: f32 = 100.0;
+/// The **Rms** window length used by the builder when none is specified.
+pub const DEFAULT_RMS_WINDOW_FRAMES: usize = 100;
+
+
+/// Fluently configures and builds an **EnvelopeDetector**.
+///
+/// Times may be given either as a number of frames or, once a sample rate has been given via
+/// `sample_rate`, in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EnvelopeDetectorBuilder {
+    attack: Time,
+    release: Time,
+    rms_window: Time,
+    sample_hz: Option<f64>,
+    ballistics: Ballistics,
+    oversample_factor: f32,
+}
+
+/// A time given to the builder which is resolved to a number of frames when building.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Time {
+    Frames(f64),
+    Ms(f64),
+}
+
+
+impl Time {
+    /// Resolve the time to a number of frames.
+    fn frames(self, sample_hz: Option<f64>) -> f64 {
+        match self {
+            Time::Frames(frames) => frames,
+            Time::Ms(ms) => {
+                let sample_hz = sample_hz
+                    .expect("a `sample_rate` must be given in order to specify times in ms");
+                ::ms_frames(ms, sample_hz)
+            },
+        }
+    }
+}
+
+impl Default for EnvelopeDetectorBuilder {
+    fn default() -> Self {
+        EnvelopeDetectorBuilder {
+            attack: Time::Frames(DEFAULT_ATTACK_FRAMES as f64),
+            release: Time::Frames(DEFAULT_RELEASE_FRAMES as f64),
+            rms_window: Time::Frames(DEFAULT_RMS_WINDOW_FRAMES as f64),
+            sample_hz: None,
+            ballistics: Ballistics::Digital,
+            oversample_factor: 1.0,
+        }
+    }
+}
+
+impl EnvelopeDetectorBuilder {
+
+    /// Begin building a new **EnvelopeDetector** with the default attack, release and window
+    /// lengths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sample rate used to convert times in milliseconds to frames.
+    pub fn sample_rate(mut self, sample_hz: f64) -> Self {
+        self.sample_hz = Some(sample_hz);
+        self
+    }
+
+    /// The **Ballistics** used to convert the attack and release times to gains.
+    ///
+    /// Defaults to **Ballistics::Digital**.
+    pub fn ballistics(mut self, ballistics: Ballistics) -> Self {
+        self.ballistics = ballistics;
+        self
+    }
+
+    /// The factor at which the built detector runs above the base sample rate.
+    ///
+    /// All times are given at the base rate. See **EnvelopeDetector::set_oversample_factor**.
+    pub fn oversample_factor(mut self, oversample_factor: f32) -> Self {
+        self.oversample_factor = oversample_factor;
+        self
+    }
+
+    /// The attack time as a number of frames.
+    pub fn attack_frames(mut self, frames: f64) -> Self {
+        self.attack = Time::Frames(frames);
+        self
+    }
+
+    /// The release time as a number of frames.
+    pub fn release_frames(mut self, frames: f64) -> Self {
+        self.release = Time::Frames(frames);
+        self
+    }
+
+    /// The length of the **Rms** window as a number of frames.
+    ///
+    /// This is only used by `build_rms`.
+    pub fn rms_window_frames(mut self, frames: usize) -> Self {
+        self.rms_window = Time::Frames(frames as f64);
+        self
+    }
+
+    /// The attack time in milliseconds.
+    ///
+    /// Requires that a `sample_rate` is given before building.
+    pub fn attack_ms(mut self, ms: f64) -> Self {
+        self.attack = Time::Ms(ms);
+        self
+    }
+
+    /// The release time in milliseconds.
+    ///
+    /// Requires that a `sample_rate` is given before building.
+    pub fn release_ms(mut self, ms: f64) -> Self {
+        self.release = Time::Ms(ms);
+        self
+    }
+
+    /// The length of the **Rms** window in milliseconds, rounded to the nearest frame.
+    ///
+    /// Requires that a `sample_rate` is given before building. This is only used by `build_rms`.
+    pub fn rms_window_ms(mut self, ms: f64) -> Self {
+        self.rms_window = Time::Ms(ms);
+        self
+    }
+
+    /// Apply the ballistics, oversample factor, attack and release to the given detector.
+    ///
+    /// The times are applied last so that their gains account for the ballistics and oversample
+    /// factor, and at `f64` precision so that times given in milliseconds are not first rounded to
+    /// `f32` frame counts.
+    fn configure<F, M>(self, detector: &mut EnvelopeDetector<F, M>)
+        where F: Frame,
+              F::Sample: FullScale,
+              M: Mode<F>,
+    {
+        detector.set_ballistics(self.ballistics);
+        detector.set_oversample_factor(self.oversample_factor);
+        detector.set_attack_frames_f64(self.attack.frames(self.sample_hz));
+        detector.set_release_frames_f64(self.release.frames(self.sample_hz));
+    }
+
+    /// Build a full wave **Peak** **EnvelopeDetector**.
+    ///
+    /// **Panics** if any time was given in milliseconds without a `sample_rate`.
+    pub fn build_peak<F>(self) -> EnvelopeDetector<F, Peak<FullWave>>
+        where F: Frame,
+              F::Sample: FullScale,
+    {
+        let mut detector = EnvelopeDetector::peak(0.0, 0.0);
+        self.configure(&mut detector);
+        detector
+    }
+
+    /// Build an **Rms** **EnvelopeDetector**.
+    ///
+    /// **Panics** if any time was given in milliseconds without a `sample_rate`.
+    pub fn build_rms<F>(self) -> EnvelopeDetector<F, Rms<F>>
+        where F: Frame,
+              F::Sample: FullScale,
+    {
+        let window_frames = self.rms_window.frames(self.sample_hz).round() as usize;
+        let mut detector = EnvelopeDetector::rms(window_frames, 0.0, 0.0);
+        self.configure(&mut detector);
+        detector
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_from_milliseconds_and_frames() {
+        let builder = EnvelopeDetectorBuilder::new()
+            .sample_rate(44_100.0)
+            .attack_ms(1.0)
+            .release_frames(20.0)
+            .rms_window_ms(10.0);
+        // 1ms at 44.1kHz is 44.1 frames, which the builder retains at `f64` precision.
+        let mut expected = EnvelopeDetector::rms(441, 0.0, 20.0);
+        expected.set_attack_frames_f64(44.1);
+        assert_eq!(builder.build_rms::<[f32; 2]>(), expected);
+
+        let builder = EnvelopeDetectorBuilder::new()
+            .attack_frames(44.0)
+            .release_frames(20.0)
+            .rms_window_frames(441);
+        assert_eq!(builder.build_rms::<[f32; 2]>(), EnvelopeDetector::rms(441, 44.0, 20.0));
+        assert_eq!(builder.build_peak::<[f32; 2]>(), EnvelopeDetector::peak(44.0, 20.0));
+    }
+
+    #[test]
+    fn defaults_to_a_peak_detector_with_a_release() {
+        let detector = EnvelopeDetectorBuilder::new().build_peak::<[f32; 2]>();
+        let expected = EnvelopeDetector::peak(DEFAULT_ATTACK_FRAMES, DEFAULT_RELEASE_FRAMES);
+        assert_eq!(detector, expected);
+        assert_eq!(detector.ballistics(), Ballistics::Digital);
+        let detector = EnvelopeDetectorBuilder::new().build_rms::<[f32; 2]>();
+        assert_eq!(detector, EnvelopeDetector::rms(DEFAULT_RMS_WINDOW_FRAMES,
+                                                   DEFAULT_ATTACK_FRAMES,
+                                                   DEFAULT_RELEASE_FRAMES));
+    }
+
+    #[test]
+    fn applies_the_ballistics_and_oversample_factor() {
+        let detector = EnvelopeDetectorBuilder::new()
+            .attack_frames(100.0)
+            .ballistics(Ballistics::Analog)
+            .oversample_factor(4.0)
+            .build_peak::<[f32; 1]>();
+        let mut expected = EnvelopeDetector::<[f32; 1], _>::with_ballistics(Peak::full_wave(),
+                                                                            100.0,
+                                                                            100.0,
+                                                                            Ballistics::Analog);
+        expected.set_oversample_factor(4.0);
+        assert_eq!(detector, expected);
+        assert_eq!(detector.ballistics(), Ballistics::Analog);
+        assert!((detector.attack_frames() - 100.0).abs() < 1e-4);
+    }
+}
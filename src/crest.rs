@@ -0,0 +1,128 @@
+//! Crest factor (peak-to-RMS ratio) detection over a signal.
+//!
+//! The primary type of interest in this module is the
+//! [**CrestFactorDetector**](./struct.CrestFactorDetector).
+
+use {EnvelopeDetector, FullScale, PeakEnvelopeDetector, Rms};
+use sample::{FloatSample, Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// Tracks the crest factor of a signal, the ratio between its peak envelope and its RMS.
+///
+/// A full wave **Peak** **EnvelopeDetector** and an **Rms** are run over the same input. A crest
+/// factor near `1.0` indicates sustained material (i.e. a square wave), while higher values
+/// indicate more percussive material (a sine wave has a crest factor of `√2`).
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>")))]
+pub struct CrestFactorDetector<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    peak: PeakEnvelopeDetector<F>,
+    rms: Rms<F>,
+}
+
+impl<F> std::fmt::Debug for CrestFactorDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Sample: FullScale,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "CrestFactorDetector {{ peak: {:?}, rms: {:?} }}", &self.peak, &self.rms)
+    }
+}
+
+
+impl<F> CrestFactorDetector<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **CrestFactorDetector**.
+    ///
+    /// The attack and release are applied to the peak envelope, while the RMS is calculated over
+    /// a window of `rms_window_frames`.
+    pub fn new(rms_window_frames: usize, attack_frames: f32, release_frames: f32) -> Self {
+        CrestFactorDetector {
+            peak: EnvelopeDetector::peak(attack_frames, release_frames),
+            rms: Rms::new(rms_window_frames),
+        }
+    }
+
+    /// A reference to the inner peak **EnvelopeDetector**.
+    pub fn peak(&self) -> &PeakEnvelopeDetector<F> {
+        &self.peak
+    }
+
+    /// A mutable reference to the inner peak **EnvelopeDetector**.
+    pub fn peak_mut(&mut self) -> &mut PeakEnvelopeDetector<F> {
+        &mut self.peak
+    }
+
+    /// A reference to the inner **Rms**.
+    pub fn rms(&self) -> &Rms<F> {
+        &self.rms
+    }
+
+    /// A mutable reference to the inner **Rms**.
+    pub fn rms_mut(&mut self) -> &mut Rms<F> {
+        &mut self.rms
+    }
+
+    /// Reset both the peak envelope and the **Rms** window.
+    pub fn reset(&mut self) {
+        self.peak.reset();
+        self.rms.reset();
+    }
+
+    /// Given the next input signal frame, return the crest factor for each channel.
+    ///
+    /// Yields `1.0` for channels whose RMS is at equilibrium (i.e. silence), avoiding a division
+    /// by zero.
+    pub fn next(&mut self, frame: F) -> F::Float {
+        let peak = self.peak.next(frame).to_float_frame();
+        let rms = self.rms.next(frame);
+        peak.zip_map(rms, |p, r| {
+            if r == Sample::equilibrium() { FloatSample::identity() } else { p / r }
+        })
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crest_factor_of_a_sine_is_root_two() {
+        let mut detector = CrestFactorDetector::<[f32; 1]>::new(1000, 0.0, 100_000.0);
+        let mut crest = [0.0];
+        for i in 0..20_000 {
+            crest = detector.next([(i as f32 * 0.0628).sin()]);
+        }
+        assert!((crest[0] - 2f32.sqrt()).abs() < 0.02, "{:?}", crest);
+    }
+
+    #[test]
+    fn crest_factor_of_a_square_wave_is_one() {
+        let mut detector = CrestFactorDetector::<[f32; 1]>::new(100, 0.0, 1000.0);
+        let mut crest = [0.0];
+        for i in 0..2000 {
+            crest = detector.next([if (i / 10) % 2 == 0 { 1.0 } else { -1.0 }]);
+        }
+        assert!((crest[0] - 1.0).abs() < 0.01, "{:?}", crest);
+    }
+
+    #[test]
+    fn crest_factor_of_silence_is_one() {
+        let mut detector = CrestFactorDetector::<[f32; 1]>::new(10, 0.0, 0.0);
+        assert_eq!(detector.next([0.0]), [1.0]);
+    }
+}
@@ -0,0 +1,150 @@
+//! An **EnvelopeDetector** that yields one envelope frame per block of input frames.
+//!
+//! The primary type of interest in this module is the
+//! [**DecimatedEnvelopeDetector**](./struct.DecimatedEnvelopeDetector).
+
+use {EnvelopeDetector, FullScale, Mode};
+use sample::Frame;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// Pairs an **EnvelopeDetector** with a decimator, i.e. for drawing meters that only require one
+/// value per block of frames.
+///
+/// The inner detector is updated with every frame, while the greatest envelope (by magnitude) in
+/// each channel is accumulated over each block of `decim` frames. Yielding the block maximum,
+/// rather than every `decim`th envelope frame, ensures that short peaks are not lost.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>")))]
+pub struct DecimatedEnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    detector: EnvelopeDetector<F, M>,
+    /// The number of frames in each block.
+    decim: usize,
+    /// The number of frames accumulated within the current block.
+    count: usize,
+    /// The greatest envelope within the current block.
+    max: F,
+}
+
+impl<F, M> std::fmt::Debug for DecimatedEnvelopeDetector<F, M>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "DecimatedEnvelopeDetector {{ detector: {:?}, decim: {:?}, count: {:?}, \
+                   max: {:?} }}",
+               &self.detector, &self.decim, &self.count, &self.max)
+    }
+}
+
+
+impl<F, M> DecimatedEnvelopeDetector<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// Construct a new **DecimatedEnvelopeDetector** that yields one envelope frame for every
+    /// `decim` input frames.
+    ///
+    /// A `decim` of `0` is treated as `1`.
+    pub fn new(detector: EnvelopeDetector<F, M>, decim: usize) -> Self {
+        DecimatedEnvelopeDetector {
+            detector,
+            decim: if decim > 1 { decim } else { 1 },
+            count: 0,
+            max: F::equilibrium(),
+        }
+    }
+
+    /// The number of input frames for each envelope frame yielded.
+    pub fn decim(&self) -> usize {
+        self.decim
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M> {
+        &mut self.detector
+    }
+
+    /// Reset the inner **EnvelopeDetector** and begin a new block.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.count = 0;
+        self.max = F::equilibrium();
+    }
+
+    /// Given the next input signal frame, update the envelope and, if this frame completes a
+    /// block, return the greatest envelope within the block.
+    ///
+    /// Returns `None` for all other frames.
+    pub fn next_decimated(&mut self, frame: F) -> Option<F> {
+        let env_frame = self.detector.next(frame);
+        let max = if self.count == 0 {
+            env_frame
+        } else {
+            self.max.zip_map(env_frame, |m, e| {
+                if ::magnitude(e) > ::magnitude(m) { e } else { m }
+            })
+        };
+        self.count += 1;
+        if self.count == self.decim {
+            self.count = 0;
+            self.max = F::equilibrium();
+            Some(max)
+        } else {
+            self.max = max;
+            None
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yields_the_maximum_envelope_of_each_block() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 30.0);
+        let mut decimated = DecimatedEnvelopeDetector::new(detector, 100);
+        let mut reference = detector;
+        let mut max = 0.0f32;
+        let mut n_yielded = 0;
+        for i in 0..1000 {
+            let x = [((i * 7919) % 1000) as f32 / 1000.0];
+            max = max.max(reference.next(x)[0]);
+            if let Some(env) = decimated.next_decimated(x) {
+                assert_eq!(env, [max]);
+                max = 0.0;
+                n_yielded += 1;
+            }
+        }
+        assert_eq!(n_yielded, 10);
+    }
+
+    #[test]
+    fn zero_decim_is_treated_as_one() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+        let mut decimated = DecimatedEnvelopeDetector::new(detector, 0);
+        assert_eq!(decimated.decim(), 1);
+        assert_eq!(decimated.next_decimated([0.5]), Some([0.5]));
+    }
+}
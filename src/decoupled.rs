@@ -0,0 +1,126 @@
+//! A decoupled peak envelope detector.
+//!
+//! The primary type of interest in this module is the [**DecoupledPeak**](./struct.DecoupledPeak)
+//! detector.
+
+use {FullScale, frames_from_gain, gain_from_frames};
+use peak::{FullWave, Rectifier};
+use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+
+/// A full wave peak envelope detector with separate release and attack stages.
+///
+/// This is the "smooth decoupled" peak detector topology described by Giannoulis et al. The first
+/// stage follows the rectified signal instantly on the way up and applies the release on the way
+/// down. The second stage then smooths the output of the first stage using the attack. As the
+/// two stages maintain separate state, sustained peaks produce an envelope free of the ripple
+/// that the single-pole **EnvelopeDetector** exhibits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub struct DecoupledPeak<F>
+    where F: Frame,
+{
+    attack_gain: f32,
+    release_gain: f32,
+    /// The state of the first (max with release) stage.
+    release_frame: F::Float,
+    /// The state of the second (attack smoothing) stage.
+    last_env_frame: F::Float,
+}
+
+
+impl<F> DecoupledPeak<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **DecoupledPeak** detector.
+    pub fn new(attack_frames: f32, release_frames: f32) -> Self {
+        DecoupledPeak {
+            attack_gain: gain_from_frames(attack_frames),
+            release_gain: gain_from_frames(release_frames),
+            release_frame: Frame::equilibrium(),
+            last_env_frame: Frame::equilibrium(),
+        }
+    }
+
+    /// Set the attack time as a number of frames.
+    pub fn set_attack_frames(&mut self, frames: f32) {
+        self.attack_gain = gain_from_frames(frames);
+    }
+
+    /// Set the release time as a number of frames.
+    pub fn set_release_frames(&mut self, frames: f32) {
+        self.release_gain = gain_from_frames(frames);
+    }
+
+    /// The attack time as a number of frames.
+    pub fn attack_frames(&self) -> f32 {
+        frames_from_gain(self.attack_gain)
+    }
+
+    /// The release time as a number of frames.
+    pub fn release_frames(&self) -> f32 {
+        frames_from_gain(self.release_gain)
+    }
+
+    /// Reset both stages to equilibrium.
+    pub fn reset(&mut self) {
+        self.release_frame = Frame::equilibrium();
+        self.last_env_frame = Frame::equilibrium();
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame.
+    pub fn next(&mut self, frame: F) -> F {
+        let attack_gain: <F::Sample as Sample>::Float = self.attack_gain.to_sample();
+        let release_gain: <F::Sample as Sample>::Float = self.release_gain.to_sample();
+        let rectified = FullWave::rectify(frame).to_float_frame();
+        self.release_frame = self.release_frame.zip_map(rectified, |r, x| {
+            if x > r { x } else { x + (r - x) * release_gain }
+        });
+        self.last_env_frame = self.last_env_frame.zip_map(self.release_frame, |l, r| {
+            r + (l - r) * attack_gain
+        });
+        self.last_env_frame.map(|s| s.to_sample())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EnvelopeDetector;
+
+    #[test]
+    fn ripples_less_than_a_single_stage_detector() {
+        let mut decoupled = DecoupledPeak::<[f32; 1]>::new(10.0, 2000.0);
+        let mut single = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 2000.0);
+        let (mut decoupled_range, mut single_range) = ((1.0f32, 0.0f32), (1.0f32, 0.0f32));
+        for i in 0..20_000 {
+            let x = [(i as f32 * 0.05).sin()];
+            let (a, b) = (decoupled.next(x)[0], single.next(x)[0]);
+            if i > 15_000 {
+                decoupled_range = (decoupled_range.0.min(a), decoupled_range.1.max(a));
+                single_range = (single_range.0.min(b), single_range.1.max(b));
+            }
+        }
+        let decoupled_ripple = decoupled_range.1 - decoupled_range.0;
+        let single_ripple = single_range.1 - single_range.0;
+        assert!(decoupled_ripple < single_ripple, "{} {}", decoupled_ripple, single_ripple);
+    }
+
+    #[test]
+    fn reset_returns_to_equilibrium() {
+        let mut decoupled = DecoupledPeak::<[f32; 1]>::new(10.0, 100.0);
+        for _ in 0..100 {
+            decoupled.next([1.0]);
+        }
+        decoupled.reset();
+        assert_eq!(decoupled.next([0.0]), [0.0]);
+    }
+}
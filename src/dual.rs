@@ -0,0 +1,106 @@
+//! Simultaneous peak and RMS envelope detection over a signal.
+//!
+//! The primary type of interest in this module is the
+//! [**DualEnvelopeDetector**](./struct.DualEnvelopeDetector).
+
+use {FullScale, PeakEnvelopeDetector, RmsEnvelopeDetector};
+use sample::Frame;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// Detects both the full wave peak envelope and the RMS envelope of a signal in a single pass.
+///
+/// Each envelope has its own independent attack and release.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>")))]
+pub struct DualEnvelopeDetector<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    peak: PeakEnvelopeDetector<F>,
+    rms: RmsEnvelopeDetector<F>,
+}
+
+impl<F> std::fmt::Debug for DualEnvelopeDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Sample: FullScale,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "DualEnvelopeDetector {{ peak: {:?}, rms: {:?} }}", &self.peak, &self.rms)
+    }
+}
+
+
+impl<F> DualEnvelopeDetector<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **DualEnvelopeDetector** from the given peak and RMS detectors.
+    pub fn new(peak: PeakEnvelopeDetector<F>, rms: RmsEnvelopeDetector<F>) -> Self {
+        DualEnvelopeDetector {
+            peak,
+            rms,
+        }
+    }
+
+    /// A reference to the inner peak **EnvelopeDetector**.
+    pub fn peak(&self) -> &PeakEnvelopeDetector<F> {
+        &self.peak
+    }
+
+    /// A mutable reference to the inner peak **EnvelopeDetector**.
+    pub fn peak_mut(&mut self) -> &mut PeakEnvelopeDetector<F> {
+        &mut self.peak
+    }
+
+    /// A reference to the inner RMS **EnvelopeDetector**.
+    pub fn rms(&self) -> &RmsEnvelopeDetector<F> {
+        &self.rms
+    }
+
+    /// A mutable reference to the inner RMS **EnvelopeDetector**.
+    pub fn rms_mut(&mut self) -> &mut RmsEnvelopeDetector<F> {
+        &mut self.rms
+    }
+
+    /// Reset both envelopes.
+    pub fn reset(&mut self) {
+        self.peak.reset();
+        self.rms.reset();
+    }
+
+    /// Given the next input signal frame, detect and return the next `(peak, rms)` envelope
+    /// frames.
+    pub fn next(&mut self, frame: F) -> (F, F) {
+        (self.peak.next(frame), self.rms.next(frame))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EnvelopeDetector;
+
+    #[test]
+    fn yields_the_same_envelopes_as_separate_detectors() {
+        let mut peak = EnvelopeDetector::<[f32; 2], _>::peak(2.0, 20.0);
+        let mut rms = EnvelopeDetector::<[f32; 2], _>::rms(8, 2.0, 20.0);
+        let mut dual = DualEnvelopeDetector::new(peak, rms.clone());
+        for i in 0..200 {
+            let x = [(i as f32 * 0.1).sin(), 0.25];
+            assert_eq!(dual.next(x), (peak.next(x), rms.next(x)));
+        }
+        dual.reset();
+        assert_eq!(dual.peak().current(), [0.0, 0.0]);
+        assert_eq!(dual.rms().current(), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,330 @@
+//! Noise gates driven by an **EnvelopeDetector**.
+//!
+//! The primary type of interest in this module is the [**Gate**](./struct.Gate). The
+//! [**GateEnvelope**](./struct.GateEnvelope) builds upon it to produce a smooth gain envelope
+//! with distinct attack, hold and release stages.
+
+use {EnvelopeDetector, FullScale, Mode, Peak};
+use peak::FullWave;
+use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// A noise gate with hysteresis.
+///
+/// The gate opens once the envelope rises to the `open_threshold_db` and only closes once the
+/// envelope falls below the lower `close_threshold_db`. This avoids chatter when the level of
+/// the signal hovers around a single threshold. The attack and release of the inner
+/// **EnvelopeDetector** act as the gate's timing.
+///
+/// For multi-channel frames the gate is driven by the loudest channel of the envelope.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>")))]
+pub struct Gate<F, M=Peak<FullWave>>
+    where F: Frame,
+          M: Mode<F>,
+{
+    detector: EnvelopeDetector<F, M>,
+    open_threshold_db: f32,
+    close_threshold_db: f32,
+    is_open: bool,
+}
+
+impl<F, M> std::fmt::Debug for Gate<F, M>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Gate {{ detector: {:?}, open_threshold_db: {:?}, close_threshold_db: {:?}, \
+                   is_open: {:?} }}",
+               &self.detector, &self.open_threshold_db, &self.close_threshold_db, &self.is_open)
+    }
+}
+
+
+impl<F, M> Gate<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// Construct a new, closed **Gate**.
+    ///
+    /// If `close_threshold_db` is greater than `open_threshold_db` it is lowered to match.
+    pub fn new(detector: EnvelopeDetector<F, M>,
+               open_threshold_db: f32,
+               close_threshold_db: f32) -> Self
+    {
+        let mut gate = Gate {
+            detector,
+            open_threshold_db,
+            close_threshold_db,
+            is_open: false,
+        };
+        gate.set_thresholds_db(open_threshold_db, close_threshold_db);
+        gate
+    }
+
+    /// Set the thresholds at which the gate opens and closes in dBFS.
+    ///
+    /// If `close_threshold_db` is greater than `open_threshold_db` it is lowered to match.
+    pub fn set_thresholds_db(&mut self, open_threshold_db: f32, close_threshold_db: f32) {
+        self.open_threshold_db = open_threshold_db;
+        self.close_threshold_db = if close_threshold_db > open_threshold_db {
+            open_threshold_db
+        } else {
+            close_threshold_db
+        };
+    }
+
+    /// The threshold at or above which the gate opens in dBFS.
+    pub fn open_threshold_db(&self) -> f32 {
+        self.open_threshold_db
+    }
+
+    /// The threshold below which the gate closes in dBFS.
+    pub fn close_threshold_db(&self) -> f32 {
+        self.close_threshold_db
+    }
+
+    /// Whether or not the gate is currently open.
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M> {
+        &mut self.detector
+    }
+
+    /// Reset the inner **EnvelopeDetector** and close the gate.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.is_open = false;
+    }
+
+    /// Given the next input signal frame, return the frame along with whether or not the gate is
+    /// open.
+    pub fn next(&mut self, frame: F) -> (F, bool) {
+        let level_db = level_db(self.detector.next(frame));
+        if self.is_open {
+            if level_db < self.close_threshold_db {
+                self.is_open = false;
+            }
+        } else if level_db >= self.open_threshold_db {
+            self.is_open = true;
+        }
+        (frame, self.is_open)
+    }
+
+}
+
+/// A noise gate that produces a gain multiplier with distinct attack, hold and release stages.
+///
+/// While the inner **Gate** is open the gain ramps linearly up to `1.0` over `attack_frames`.
+/// Once the **Gate** closes, the gain is held for `hold_frames` before ramping linearly down to
+/// `0.0` over `release_frames`. Should the **Gate** re-open during the hold or release, the gain
+/// ramps back up from its current value.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>")))]
+pub struct GateEnvelope<F, M=Peak<FullWave>>
+    where F: Frame,
+          M: Mode<F>,
+{
+    gate: Gate<F, M>,
+    attack_frames: usize,
+    hold_frames: usize,
+    release_frames: usize,
+    /// The number of frames remaining in the current hold period.
+    hold_remaining: usize,
+    /// The current gain multiplier in the range `[0.0, 1.0]`.
+    gain: f32,
+}
+
+impl<F, M> std::fmt::Debug for GateEnvelope<F, M>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "GateEnvelope {{ gate: {:?}, attack_frames: {:?}, hold_frames: {:?}, \
+                   release_frames: {:?}, hold_remaining: {:?}, gain: {:?} }}",
+               &self.gate, &self.attack_frames, &self.hold_frames, &self.release_frames,
+               &self.hold_remaining, &self.gain)
+    }
+}
+
+
+impl<F, M> GateEnvelope<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// Construct a new, closed **GateEnvelope** from the given **Gate** and stage times.
+    ///
+    /// Attack and release times of `0` frames are instantaneous.
+    pub fn new(gate: Gate<F, M>,
+               attack_frames: usize,
+               hold_frames: usize,
+               release_frames: usize) -> Self
+    {
+        GateEnvelope {
+            gate,
+            attack_frames,
+            hold_frames,
+            release_frames,
+            hold_remaining: 0,
+            gain: 0.0,
+        }
+    }
+
+    /// The number of frames over which the gain ramps up once the gate opens.
+    pub fn attack_frames(&self) -> usize {
+        self.attack_frames
+    }
+
+    /// The number of frames for which the gain is held once the gate closes.
+    pub fn hold_frames(&self) -> usize {
+        self.hold_frames
+    }
+
+    /// The number of frames over which the gain ramps down once the hold period expires.
+    pub fn release_frames(&self) -> usize {
+        self.release_frames
+    }
+
+    /// Set the number of frames over which the gain ramps up once the gate opens.
+    pub fn set_attack_frames(&mut self, frames: usize) {
+        self.attack_frames = frames;
+    }
+
+    /// Set the number of frames for which the gain is held once the gate closes.
+    ///
+    /// This takes effect the next time the gate closes.
+    pub fn set_hold_frames(&mut self, frames: usize) {
+        self.hold_frames = frames;
+    }
+
+    /// Set the number of frames over which the gain ramps down once the hold period expires.
+    pub fn set_release_frames(&mut self, frames: usize) {
+        self.release_frames = frames;
+    }
+
+    /// The current gain multiplier in the range `[0.0, 1.0]`.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// A reference to the inner **Gate**.
+    pub fn gate(&self) -> &Gate<F, M> {
+        &self.gate
+    }
+
+    /// A mutable reference to the inner **Gate**.
+    pub fn gate_mut(&mut self) -> &mut Gate<F, M> {
+        &mut self.gate
+    }
+
+    /// Reset the inner **Gate** and close the gain envelope.
+    pub fn reset(&mut self) {
+        self.gate.reset();
+        self.hold_remaining = 0;
+        self.gain = 0.0;
+    }
+
+    /// Given the next input signal frame, return the gain multiplier to apply to it.
+    pub fn next(&mut self, frame: F) -> f32 {
+        let (_, is_open) = self.gate.next(frame);
+        if is_open {
+            self.hold_remaining = self.hold_frames;
+            self.gain = ramp_up(self.gain, self.attack_frames);
+        } else if self.hold_remaining > 0 {
+            self.hold_remaining -= 1;
+        } else {
+            self.gain = ramp_down(self.gain, self.release_frames);
+        }
+        self.gain
+    }
+
+}
+
+/// Step the given gain linearly towards `1.0` such that a full ramp spans `frames`.
+fn ramp_up(gain: f32, frames: usize) -> f32 {
+    let gain = if frames == 0 { 1.0 } else { gain + 1.0 / frames as f32 };
+    if gain > 1.0 { 1.0 } else { gain }
+}
+
+/// Step the given gain linearly towards `0.0` such that a full ramp spans `frames`.
+fn ramp_down(gain: f32, frames: usize) -> f32 {
+    let gain = if frames == 0 { 0.0 } else { gain - 1.0 / frames as f32 };
+    if gain < 0.0 { 0.0 } else { gain }
+}
+
+/// The level of the loudest channel of the given envelope frame in dBFS.
+fn level_db<F>(env_frame: F) -> f32
+    where F: Frame,
+{
+    let amp = env_frame.channels().fold(0.0, |max, s| {
+        let amp = s.to_float_sample().to_sample::<f32>().abs();
+        if amp > max { amp } else { max }
+    });
+    ::amp_to_db(amp, ::LOG_DOMAIN_FLOOR_DB)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate() -> Gate<[f32; 1]> {
+        Gate::new(EnvelopeDetector::peak(0.0, 0.0), -20.0, -30.0)
+    }
+
+    #[test]
+    fn gate_holds_its_state_between_the_thresholds() {
+        let mut gate = gate();
+        assert!(!gate.next([0.05]).1);
+        assert!(gate.next([0.2]).1);
+        for i in 0..50 {
+            assert!(gate.next([if i % 2 == 0 { 0.05 } else { 0.08 }]).1);
+        }
+        assert!(!gate.next([0.01]).1);
+        for i in 0..50 {
+            assert!(!gate.next([if i % 2 == 0 { 0.05 } else { 0.08 }]).1);
+        }
+    }
+
+    #[test]
+    fn gate_envelope_ramps_through_attack_hold_and_release() {
+        let mut envelope = GateEnvelope::new(gate(), 10, 20, 40);
+        let gains: Vec<f32> = (0..200)
+            .map(|i| envelope.next([if (10..60).contains(&i) { 0.5 } else { 0.0 }]))
+            .collect();
+        assert_eq!(gains[9], 0.0);
+        assert!((gains[14] - 0.5).abs() < 1e-5, "{}", gains[14]);
+        assert_eq!(gains[19], 1.0);
+        assert_eq!(gains[59], 1.0);
+        assert!(gains[60..80].iter().all(|&gain| gain == 1.0), "{:?}", &gains[60..80]);
+        assert!((gains[99] - 0.5).abs() < 1e-4, "{}", gains[99]);
+        assert_eq!(gains[120], 0.0);
+    }
+}
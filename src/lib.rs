@@ -5,20 +5,91 @@
 //! - [**EnvelopeDetector**](./struct.EnvelopeDetector).
 //! - [**Rms**](./rms.struct.Rms).
 //! - [**Peak**](./peak.struct.Peak).
+//! - [**TruePeak**](./peak.struct.TruePeak).
+//! - [**CrestFactorDetector**](./crest.struct.CrestFactorDetector).
+//! - [**DecimatedEnvelopeDetector**](./decimated.struct.DecimatedEnvelopeDetector).
+//! - [**DecoupledPeak**](./decoupled.struct.DecoupledPeak).
+//! - [**DualEnvelopeDetector**](./dual.struct.DualEnvelopeDetector).
+//! - [**EnvelopeDetectorBuilder**](./builder.struct.EnvelopeDetectorBuilder).
+//! - [**Gate**](./gate.struct.Gate).
+//! - [**LookaheadEnvelopeDetector**](./lookahead.struct.LookaheadEnvelopeDetector).
+//! - [**Loudness**](./loudness.struct.Loudness).
+//! - [**MeterScale**](./meter.struct.MeterScale).
+//! - [**MinMaxDetector**](./minmax.struct.MinMaxDetector).
+//! - [**MultibandEnvelopeDetector**](./multiband.struct.MultibandEnvelopeDetector).
+//! - [**TimedEnvelopeDetector**](./timed.struct.TimedEnvelopeDetector).
+//! - [**AWeight**](./weighting.struct.AWeight) and [**KWeight**](./weighting.struct.KWeight).
+//!
+//! ### `rayon`
+//!
+//! The optional `rayon` feature provides the [**parallel**](./parallel/index.html) module for
+//! processing the channels of a signal across threads.
+//!
+//! ### `simd`
+//!
+//! The optional `simd` feature provides **Rms::next_simd**, which uses the `wide` crate to
+//! vectorise the RMS calculation across the channels of `f32` frames.
 
 #![deny(missing_copy_implementations)]
 #![deny(missing_docs)]
 
 extern crate sample;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+#[cfg(feature = "simd")]
+extern crate wide;
 
-pub use mode::Mode;
-pub use peak::Peak;
-pub use rms::Rms;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sample::FloatSample;
+use std::time::Duration;
+
+pub use builder::EnvelopeDetectorBuilder;
+pub use crest::CrestFactorDetector;
+pub use decimated::DecimatedEnvelopeDetector;
+pub use decoupled::DecoupledPeak;
+pub use dual::DualEnvelopeDetector;
+pub use gate::{Gate, GateEnvelope};
+pub use lookahead::LookaheadEnvelopeDetector;
+pub use loudness::Loudness;
+pub use meter::MeterScale;
+pub use minmax::MinMaxDetector;
+pub use mode::{AnyMode, HoldRelease, Mode, PeakHold, ProgramDependentRelease, WindowedPeak};
+pub use multiband::MultibandEnvelopeDetector;
+#[cfg(feature = "rayon")]
+pub use parallel::process_channels_par;
+pub use peak::{DynPeak, Peak, TruePeak};
+pub use rms::{ArrayRms, ExpRms, Rms, WindowShape};
 pub use sample::{Frame, Sample};
+pub use signal::{Envelope, EnvelopeZip, SignalEnvelope};
+pub use stats::EnvelopeStats;
+pub use timed::TimedEnvelopeDetector;
+pub use weighting::{AWeight, KWeight};
 
+pub mod builder;
+pub mod crest;
+pub mod decimated;
+pub mod decoupled;
+pub mod dual;
+pub mod gate;
+pub mod lookahead;
+pub mod loudness;
+pub mod meter;
+pub mod minmax;
 pub mod mode;
+pub mod multiband;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod peak;
 pub mod rms;
+pub mod signal;
+pub mod stats;
+pub mod timed;
+pub mod weighting;
 
 
 /// Iteratively extracts the amplitude envelope from an audio signal based on three parameters:
@@ -27,15 +98,63 @@ pub mod rms;
 /// - Release time.
 /// - Detection mode (Either Peak or RMS).
 ///
-/// Supports processing any `sample::Frame`
-#[derive(Copy, Clone, Debug)]
-pub struct EnvelopeDetector<F, M>
+/// Supports processing any `sample::Frame` whose sample format implements **FullScale**, which
+/// includes every format provided by the `sample` crate.
+///
+/// The attack and release gains are stored and applied at the precision of the floating point
+/// type `G` regardless of the frame type. `G` is `f32` by default. A pipeline of `f64` frames may
+/// retain full precision end-to-end (even for very long attack and release times) via a detector
+/// with `f64` gains, as produced by `with_gain_type`. Times that differ per channel (see
+/// `with_frames_per_channel`) are instead stored at the precision of `F::Float`.
+///
+/// The envelope itself is kept in `F::Float`. For modes that calculate their result in floating
+/// point (i.e. **Rms**, see **Mode::smooths_in_float**) the envelope is smoothed in `F::Float` and
+/// converted to `F` only once per frame, so that the RMS of integer frames is not truncated before
+/// smoothing. The envelope following any other mode is smoothed in `F`.
+///
+/// When the `serde` feature is enabled, the **EnvelopeDetector** may be serialized along with its
+/// full state, given that both the frame type `F` and the **Mode** `M` are serializable.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize, G: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>, \
+                                                         G: Deserialize<'de>")))]
+pub struct EnvelopeDetector<F, M, G = f32>
     where F: Frame,
           M: Mode<F>,
+          G: FloatSample,
 {
-    attack_gain: f32,
-    release_gain: f32,
-    last_env_frame: F,
+    attack_gain: G,
+    release_gain: G,
+    /// The frame counts from which the gains were last calculated, kept at `f64` precision so
+    /// that `retune` may rescale them repeatedly without accumulating rounding error.
+    attack_frames: f64,
+    release_frames: f64,
+    /// Attack and release times that differ per channel, in which case the shared gains above
+    /// are unused.
+    attack_per_channel: Option<ChannelTimes<F::Float>>,
+    release_per_channel: Option<ChannelTimes<F::Float>>,
+    ballistics: Ballistics,
+    /// The factor by which the attack and release frame counts are scaled, for detectors running
+    /// at an oversampled rate.
+    oversample_factor: f32,
+    /// The envelope, kept in the floating point representation of `F` so that modes which
+    /// calculate their result in floating point (i.e. **Rms**) are smoothed without first being
+    /// converted to `F`.
+    last_env_frame: F::Float,
+    /// The number of frames since the envelope last attacked, as tracked by `next_with_age`.
+    age: usize,
+    /// The **Phase** taken by the most recent frame, as tracked by `next_with_phase`.
+    last_phase: Phase,
+    /// The envelope of greatest magnitude in each channel since construction or the last call to
+    /// `clear_session_peak`.
+    session_peak: F,
+    /// The **Smoothing** applied by the attack and release, including any slew limit.
+    attack_smoothing: Smoothing,
+    release_smoothing: Smoothing,
     mode: M,
 }
 
@@ -43,92 +162,2932 @@ pub struct EnvelopeDetector<F, M>
 pub type RmsEnvelopeDetector<F> = EnvelopeDetector<F, Rms<F>>;
 /// An `EnvelopeDetector` that tracks the full wave `Peak` envelope of a signal.
 pub type PeakEnvelopeDetector<F> = EnvelopeDetector<F, Peak<peak::FullWave>>;
+/// An `EnvelopeDetector` that tracks the signal envelope using an exponentially weighted RMS.
+pub type ExpRmsEnvelopeDetector<F> = EnvelopeDetector<F, ExpRms<F>>;
+/// An `EnvelopeDetector` that tracks the signal envelope using an RMS whose window of `N` frames
+/// is stored inline as an array.
+pub type ArrayRmsEnvelopeDetector<F, const N: usize> = EnvelopeDetector<F, ArrayRms<F, N>>;
+/// An `EnvelopeDetector` whose detection mode may be switched at runtime.
+pub type AnyEnvelopeDetector<F> = EnvelopeDetector<F, AnyMode<F>>;
+/// An `EnvelopeDetector` that holds each new full wave peak before releasing.
+pub type PeakHoldEnvelopeDetector<F> = EnvelopeDetector<F, PeakHold<F>>;
+
+
+/// The formula by which an **EnvelopeDetector**'s attack and release times are converted to gains.
+///
+/// Both forms converge to the same steady state and are near identical for long times, however
+/// they differ noticeably for times of only a few frames.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ballistics {
+    /// The exact discrete-time one-pole response, where the gain for a time of `n` frames is
+    /// `e^(-1/n)`. A step input reaches `1 - 1/e` of its target after exactly `n` frames.
+    ///
+    /// This is the default.
+    #[default]
+    Digital,
+    /// The forward difference approximation of an analog RC filter, where each frame moves the
+    /// envelope `1/n` of the way towards its target. For times of `1.0` frames or less a step
+    /// input reaches its target within a single frame, whereas the `Digital` form only does so
+    /// for a time of `0.0` frames. For longer times the response is slightly slower than that of
+    /// the `Digital` form.
+    Analog,
+}
+
+impl Ballistics {
+
+    /// The gain for an attack or release time of `n_frames` at `f64` precision.
+    ///
+    /// For long attack and release times the gain is very close to `1.0`, so the
+    /// **EnvelopeDetector** stores and applies its gains as `f64` to avoid the quantisation of
+    /// `f32` stalling the envelope.
+    fn gain(self, n_frames: f64) -> f64 {
+        // Negative and NaN frame counts would produce a gain above `1.0` (or NaN) and cause the
+        // envelope to diverge, so we treat them as instantaneous (zero frames) instead.
+        let n_frames = valid_frames(n_frames);
+        match self {
+            Ballistics::Digital => (-1.0 / n_frames).exp(),
+            Ballistics::Analog => if n_frames > 1.0 { 1.0 - 1.0 / n_frames } else { 0.0 },
+        }
+    }
+
+    /// The inverse of `gain`.
+    fn frames(self, gain: f64) -> f32 {
+        if gain <= 0.0 {
+            0.0
+        } else if gain >= 1.0 {
+            f32::INFINITY
+        } else {
+            match self {
+                Ballistics::Digital => (-1.0 / gain.ln()) as f32,
+                Ballistics::Analog => (1.0 / (1.0 - gain)) as f32,
+            }
+        }
+    }
+
+}
+
+
+/// The shape of the path taken by an **EnvelopeDetector**'s envelope during its attack or
+/// release, as selected independently for each via **EnvelopeDetector::with_smoothing** or the
+/// slew setters such as **EnvelopeDetector::set_release_slew_db_per_sec**.
+///
+/// Except for **DbPerFrame**, the length of the stage is given by its attack or release time as
+/// a number of frames. In each case the envelope lands exactly on the detected level once it is
+/// within reach.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Smoothing {
+    /// The exponential one-pole response described by the **Ballistics**, moving a fraction of
+    /// the way towards the detected level each frame.
+    ///
+    /// This is the default.
+    #[default]
+    OnePole,
+    /// A linear ramp in amplitude at a constant rate, such that the stage traverses the full
+    /// scale range (i.e. `0.0` to `1.0`) over its time in frames.
+    Linear,
+    /// A linear ramp in decibels at a constant rate, such that the stage traverses
+    /// [**LOG_DOMAIN_FLOOR_DB**](./constant.LOG_DOMAIN_FLOOR_DB) to `0.0` dBFS over its time in
+    /// frames. This is the same as **DbPerFrame**, with the rate derived from the time of the
+    /// stage.
+    DbLinear,
+    /// A linear ramp in decibels at the given rate in decibels per frame, regardless of the time
+    /// of the stage, as set via `set_attack_slew_db_per_sec` and `set_release_slew_db_per_sec`.
+    DbPerFrame(f64),
+}
+
+impl Smoothing {
+
+    /// Resolve the **Smoothing** to the **Stage** that applies it within the **Domain** `D`.
+    ///
+    /// This is done once per frame, so that the channel loop does not match on the **Smoothing**.
+    fn stage<F, D, G>(self, ballistics: Ballistics) -> Stage<D::Sample, G>
+        where F: Frame,
+              D: Domain<F>,
+              G: FloatSample,
+    {
+        let (apply, db_per_frame): (StageFn<D::Sample, G>, f64) = match self {
+            Smoothing::OnePole => (|l, m, gain, _, _| D::apply_gain(l, m, gain), 0.0),
+            Smoothing::Linear => (|l, m, gain, ballistics, _| {
+                apply_linear(l, m, 1.0 / ballistics.frames(gain.to_sample()) as f64)
+            }, 0.0),
+            Smoothing::DbLinear => (|l, m, gain, ballistics, _| {
+                let frames = ballistics.frames(gain.to_sample());
+                apply_slew(l, m, -LOG_DOMAIN_FLOOR_DB as f64 / frames as f64)
+            }, 0.0),
+            Smoothing::DbPerFrame(db_per_frame) => {
+                (|l, m, _, _, db_per_frame| apply_slew(l, m, db_per_frame), db_per_frame)
+            },
+        };
+        Stage { apply, ballistics, db_per_frame }
+    }
+
+    /// Rescale the rate of a **DbPerFrame** slew following a change in sample rate, where
+    /// `ratio` is the new sample rate divided by the old.
+    fn retune(self, ratio: f64) -> Self {
+        match self {
+            Smoothing::DbPerFrame(db_per_frame) => Smoothing::DbPerFrame(db_per_frame / ratio),
+            smoothing => smoothing,
+        }
+    }
+
+    /// The rate in decibels per frame if this is a **DbPerFrame** slew.
+    fn db_per_frame(self) -> Option<f32> {
+        match self {
+            Smoothing::DbPerFrame(db_per_frame) => Some(db_per_frame as f32),
+            _ => None,
+        }
+    }
+
+}
+
+
+/// Moves the sample `l` a single frame towards `m`, given the one-pole gain of the stage, the
+/// **Ballistics** and the rate of a **DbPerFrame** slew.
+type StageFn<S, G> = fn(S, S, G, Ballistics, f64) -> S;
+
+/// The attack or release **Smoothing** of an **EnvelopeDetector**, resolved for a single frame.
+struct Stage<S, G> {
+    apply: StageFn<S, G>,
+    ballistics: Ballistics,
+    db_per_frame: f64,
+}
+
+impl<S, G> Stage<S, G> {
+
+    /// Move the sample `l` a single frame towards `m` for a stage whose one-pole gain is `gain`.
+    ///
+    /// The time of the stage in frames is recovered from the gain via the **Ballistics**, so that
+    /// it remains correct for per-channel times and oversampled detectors.
+    fn apply(&self, l: S, m: S, gain: G) -> S {
+        (self.apply)(l, m, gain, self.ballistics, self.db_per_frame)
+    }
+
+}
+
+
+/// Whether the envelope was attacking, releasing or holding steady on the most recent frame, as
+/// reported by **EnvelopeDetector::last_phase** and **EnvelopeDetector::next_with_phase**.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Phase {
+    /// The envelope moved towards a detected frame of greater magnitude.
+    Attack,
+    /// The envelope moved towards a detected frame of lesser magnitude.
+    Release,
+    /// The envelope did not move, i.e. as it has settled on a steady level.
+    #[default]
+    Steady,
+}
+
+/// Determines the **Phase** of a frame from the channel whose envelope moved the furthest.
+struct PhaseTracker {
+    phase: Phase,
+    largest_movement: f64,
+}
+
+impl PhaseTracker {
+
+    fn new() -> Self {
+        PhaseTracker {
+            phase: Phase::Steady,
+            largest_movement: 0.0,
+        }
+    }
+
+    /// Account for a channel whose envelope moved from `from` to `to` via the attack or release.
+    fn update<S>(&mut self, from: S, to: S, is_attack: bool)
+        where S: Sample,
+    {
+        let from = from.to_float_sample().to_sample::<f64>();
+        let to = to.to_float_sample().to_sample::<f64>();
+        let movement = if to > from { to - from } else { from - to };
+        if movement > self.largest_movement {
+            self.largest_movement = movement;
+            self.phase = if is_attack { Phase::Attack } else { Phase::Release };
+        }
+    }
+
+}
+
+
+/// The frame type in which the **EnvelopeDetector** smooths its envelope, which is stored in the
+/// floating point representation of `F`.
+trait Domain<F>
+    where F: Frame,
+{
+    /// The sample type in which the envelope is smoothed.
+    type Sample: Sample;
+    /// The frame type in which the envelope is smoothed.
+    type Frame: Frame<Sample=Self::Sample, NumChannels=F::NumChannels>;
+    /// Convert the stored envelope to the frame type in which it is smoothed.
+    fn from_float(frame: F::Float) -> Self::Frame;
+    /// Convert the smoothed envelope back to the frame type in which it is stored.
+    fn to_float(frame: Self::Frame) -> F::Float;
+    /// The distance of the given sample from equilibrium, as per **magnitude**.
+    fn magnitude(sample: Self::Sample) -> <Self::Sample as Sample>::Signed;
+    /// Move the sample `l` towards `m` by the given gain, as per **apply_gain**.
+    fn apply_gain<G>(l: Self::Sample, m: Self::Sample, gain: G) -> Self::Sample
+        where G: FloatSample;
+}
+
+/// Smooths the envelope in `F`, as for modes whose result is produced in `F` (i.e. **Peak**).
+struct FrameDomain;
+
+/// Smooths the envelope in `F::Float`, as for modes whose result is calculated in floating point
+/// (i.e. **Rms**).
+struct FloatDomain;
+
+impl<F> Domain<F> for FrameDomain
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    type Sample = F::Sample;
+    type Frame = F;
+    fn from_float(frame: F::Float) -> F {
+        frame.map(|s| s.to_sample())
+    }
+    fn to_float(frame: F) -> F::Float {
+        frame.to_float_frame()
+    }
+    fn magnitude(sample: F::Sample) -> <F::Sample as Sample>::Signed {
+        magnitude(sample)
+    }
+    fn apply_gain<G>(l: F::Sample, m: F::Sample, gain: G) -> F::Sample
+        where G: FloatSample,
+    {
+        apply_gain(l, m, gain)
+    }
+}
+
+impl<F> Domain<F> for FloatDomain
+    where F: Frame,
+{
+    type Sample = <F::Sample as Sample>::Float;
+    type Frame = F::Float;
+    fn from_float(frame: F::Float) -> F::Float {
+        frame
+    }
+    fn to_float(frame: F::Float) -> F::Float {
+        frame
+    }
+    fn magnitude(sample: Self::Sample) -> Self::Sample {
+        float_magnitude(sample)
+    }
+    fn apply_gain<G>(l: Self::Sample, m: Self::Sample, gain: G) -> Self::Sample
+        where G: FloatSample,
+    {
+        blend(l, m, gain)
+    }
+}
+
+
+/// Attack or release times that differ per channel, along with the gains calculated from them.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct ChannelTimes<F> {
+    /// The frame counts exactly as they were set.
+    frames: F,
+    /// The ratio by which `retune` has since rescaled the `frames`.
+    ///
+    /// The `frames` themselves are left untouched, as rescaling them at the precision of `F`
+    /// would accumulate rounding error over repeated calls to `retune`.
+    scale: f64,
+    gains: F,
+}
+
+/// The gain of a channel yielded by a table of per-channel gains, or the shared gain should the
+/// table be exhausted.
+fn channel_gain<S, G>(gain: Option<S>, shared: G) -> G
+    where S: Sample,
+          G: FloatSample,
+{
+    gain.map_or(shared, |g| g.to_float_sample().to_sample::<f64>().to_sample())
+}
+
+
+/// The errors that may occur when constructing an **EnvelopeDetector** from unvalidated
+/// parameters, i.e. via **EnvelopeDetector::try_rms**.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EnvelopeError {
+    /// The **Rms** window was given a length of zero frames.
+    ZeroWindow,
+    /// The attack time was infinite or NaN.
+    NonFiniteAttack,
+    /// The release time was infinite or NaN.
+    NonFiniteRelease,
+}
+
+impl std::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let description = match *self {
+            EnvelopeError::ZeroWindow => "the RMS window must be at least one frame long",
+            EnvelopeError::NonFiniteAttack => "the attack time must be finite",
+            EnvelopeError::NonFiniteRelease => "the release time must be finite",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+/// The errors that may occur when processing an interleaved sample buffer via
+/// **EnvelopeDetector::process_interleaved**.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InterleaveError {
+    /// The requested channel count does not match the number of channels in the frame type.
+    ChannelMismatch {
+        /// The channel count requested via `process_interleaved::<N>`.
+        requested: usize,
+        /// The number of channels in the detector's frame type.
+        frame: usize,
+    },
+    /// The length of the buffer is not a multiple of the channel count.
+    Misaligned {
+        /// The length of the buffer in samples.
+        len: usize,
+        /// The channel count.
+        channels: usize,
+    },
+}
+
+impl std::fmt::Display for InterleaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            InterleaveError::ChannelMismatch { requested, frame } =>
+                write!(f, "the requested channel count ({}) does not match the number of \
+                           channels in the frame ({})", requested, frame),
+            InterleaveError::Misaligned { len, channels } =>
+                write!(f, "the length of the interleaved buffer ({}) must be a multiple of the \
+                           number of channels ({})", len, channels),
+        }
+    }
+}
+
+impl std::error::Error for InterleaveError {}
+
+
+/// The level below which `next_log_domain` treats the envelope as silence.
+pub const LOG_DOMAIN_FLOOR_DB: f32 = -120.0;
+
+/// Convert a time constant τ in seconds (the time taken for a step response to reach `1 - 1/e`
+/// of its target) to a number of frames at the given sample rate.
+///
+/// The resulting frame count may be passed directly to the attack and release setters, as the
+/// **EnvelopeDetector**'s gain is calculated as `e^(-1/frames)` (see
+/// [**gain_from_frames**](./fn.gain_from_frames)).
+pub fn time_constant_frames(secs: f64, sample_hz: f64) -> f32 {
+    (secs * sample_hz) as f32
+}
+
+/// The gain used by the **EnvelopeDetector** for an attack or release time of `n_frames`.
+///
+/// This is calculated as `e^(-1/n_frames)`. Negative and NaN frame counts are treated as `0.0`,
+/// producing a gain of `0.0` (an instantaneous attack or release).
+pub fn gain_from_frames(n_frames: f32) -> f32 {
+    Ballistics::Digital.gain(n_frames as f64) as f32
+}
+
+/// The attack or release time as a number of frames that would produce the given gain.
+///
+/// This is the inverse of [**gain_from_frames**](./fn.gain_from_frames), calculated as
+/// `-1/ln(gain)`. A gain of `0.0` (or less) yields `0.0` frames, while a gain of `1.0` (or
+/// greater) yields an infinite number of frames.
+pub fn frames_from_gain(gain: f32) -> f32 {
+    Ballistics::Digital.frames(gain as f64)
+}
+
+/// Reset every **EnvelopeDetector** in the given slice, i.e. a bank of per-channel detectors
+/// upon a transport stop.
+///
+/// This is equivalent to calling `reset` on each detector in turn.
+pub fn reset_all<F, M>(detectors: &mut [EnvelopeDetector<F, M>])
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+    for detector in detectors {
+        detector.reset();
+    }
+}
+
+/// The number of frames spanned by the given duration at the given sample rate.
+fn duration_frames(duration: Duration, sample_hz: f64) -> f64 {
+    let secs = duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9;
+    secs * sample_hz
+}
 
+/// The number of frames spanned by the given number of milliseconds at the given sample rate.
+fn ms_frames(ms: f64, sample_hz: f64) -> f64 {
+    ms * sample_hz / 1_000.0
+}
+
+/// Convert a linear amplitude to decibels, clamping silence (and anything quieter than
+/// `floor_db`) to `floor_db`.
+fn amp_to_db(amp: f32, floor_db: f32) -> f32 {
+    if amp <= 0.0 {
+        return floor_db;
+    }
+    let db = 20.0 * amp.log10();
+    if db < floor_db { floor_db } else { db }
+}
+
+/// Convert decibels to a linear amplitude.
+fn db_to_amp(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
+/// The hard knee compressor gain reduction in decibels (zero or negative) for the given envelope
+/// level, threshold and ratio.
+fn calc_gain_reduction_db(env_db: f32, threshold_db: f32, ratio: f32) -> f32 {
+    if ratio.is_nan() || ratio <= 1.0 || env_db <= threshold_db {
+        return 0.0;
+    }
+    (env_db - threshold_db) * (1.0 / ratio - 1.0)
+}
+
+/// The soft knee compressor gain reduction in decibels (zero or negative) for the given envelope
+/// level, threshold, ratio and knee width.
+///
+/// Within `[threshold_db - knee_db / 2, threshold_db + knee_db / 2]` the reduction is interpolated
+/// quadratically from zero to the hard knee reduction. A `knee_db` of `0.0` or less (or NaN) is a
+/// hard knee.
+fn calc_soft_gain_reduction_db(env_db: f32, threshold_db: f32, ratio: f32, knee_db: f32) -> f32 {
+    if knee_db.is_nan() || knee_db <= 0.0 {
+        return calc_gain_reduction_db(env_db, threshold_db, ratio);
+    }
+    if ratio.is_nan() || ratio <= 1.0 {
+        return 0.0;
+    }
+    let over_db = env_db - threshold_db;
+    if 2.0 * over_db < -knee_db {
+        0.0
+    } else if 2.0 * over_db > knee_db {
+        over_db * (1.0 / ratio - 1.0)
+    } else {
+        let x = over_db + knee_db / 2.0;
+        (1.0 / ratio - 1.0) * x * x / (2.0 * knee_db)
+    }
+}
+
+/// The ducking gain reduction in decibels (zero or negative) for the given envelope level,
+/// threshold and maximum depth.
+///
+/// The reduction follows the envelope above the threshold decibel for decibel until it reaches
+/// the depth. A `depth_db` of `0.0` or less (or NaN) applies no reduction.
+fn calc_duck_db(env_db: f32, threshold_db: f32, depth_db: f32) -> f32 {
+    if depth_db.is_nan() || depth_db <= 0.0 || env_db <= threshold_db {
+        return 0.0;
+    }
+    let over_db = env_db - threshold_db;
+    -(if over_db < depth_db { over_db } else { depth_db })
+}
+
+/// The full scale range of a **Sample** format.
+///
+/// This is implemented for each of the sample formats provided by the `sample` crate, so that
+/// the limits of a format are known as constants rather than recovered by converting `1.0` and
+/// `-1.0` to it. The limits are given in the signed equivalent of the format, in which the
+/// magnitude of a sample is measured.
+pub trait FullScale: Sample {
+    /// The most negative value of the signed equivalent of the format, i.e. `-1.0` for floating
+    /// point samples and `i16::MIN` for `i16` and `u16` samples.
+    const MIN: Self::Signed;
+    /// The most positive value of the signed equivalent of the format, i.e. `1.0` for floating
+    /// point samples and `i16::MAX` for `i16` and `u16` samples.
+    const MAX: Self::Signed;
+    /// The size of a single step of the format where full scale is `1.0`, or `0.0` for floating
+    /// point formats.
+    const STEP: f64;
+}
+
+/// Implements **FullScale** for integer formats of the given number of bits.
+macro_rules! impl_full_scale {
+    ($($T:ty: $MIN:expr, $MAX:expr, $BITS:expr;)*) => {
+        $(
+            impl FullScale for $T {
+                const MIN: Self::Signed = $MIN;
+                const MAX: Self::Signed = $MAX;
+                const STEP: f64 = 1.0 / (1u64 << ($BITS - 1)) as f64;
+            }
+        )*
+    };
+}
+
+impl_full_scale! {
+    i8: i8::MIN, i8::MAX, 8;
+    i16: i16::MIN, i16::MAX, 16;
+    sample::types::I24: sample::types::i24::MIN, sample::types::i24::MAX, 24;
+    i32: i32::MIN, i32::MAX, 32;
+    sample::types::I48: sample::types::i48::MIN, sample::types::i48::MAX, 48;
+    i64: i64::MIN, i64::MAX, 64;
+    u8: i8::MIN, i8::MAX, 8;
+    u16: i16::MIN, i16::MAX, 16;
+    sample::types::U24: i32::MIN, i32::MAX, 24;
+    u32: i32::MIN, i32::MAX, 32;
+    sample::types::U48: i64::MIN, i64::MAX, 48;
+    u64: i64::MIN, i64::MAX, 64;
+}
+
+impl FullScale for f32 {
+    const MIN: f32 = -1.0;
+    const MAX: f32 = 1.0;
+    const STEP: f64 = 0.0;
+}
+
+impl FullScale for f64 {
+    const MIN: f64 = -1.0;
+    const MAX: f64 = 1.0;
+    const STEP: f64 = 0.0;
+}
+
+/// The distance of the given sample from equilibrium.
+///
+/// Used to select between attack and release so that envelopes below equilibrium (i.e. from a
+/// **NegativeHalfWave** rectifier) track magnitude in the same direction as those above it.
+fn magnitude<S>(sample: S) -> S::Signed
+    where S: FullScale,
+{
+    let signed = sample.to_signed_sample();
+    if signed < Sample::equilibrium() { saturating_neg::<S>(signed) } else { signed }
+}
+
+/// The distance of the given floating point sample from equilibrium.
+///
+/// The same as **magnitude**, for the floating point samples in which the envelope is stored.
+fn float_magnitude<S>(sample: S) -> S
+    where S: FloatSample,
+{
+    if sample < Sample::equilibrium() { -sample } else { sample }
+}
+
+/// Whether the given sample is at or beyond the maximum amplitude of its format in either
+/// direction, i.e. `1.0` or `-1.0` for floating point samples and `i16::MAX` or `i16::MIN` for
+/// `i16` samples.
+fn is_full_scale<S>(sample: S) -> bool
+    where S: FullScale,
+{
+    magnitude(sample) >= S::MAX
+}
+
+/// Negate the given sample of the signed equivalent of the format `S`, saturating at full scale.
+///
+/// The most negative value of a two's complement integer format (i.e. `i16::MIN`) has no positive
+/// counterpart, so plain negation would overflow. It is instead clamped to the maximum.
+fn saturating_neg<S>(signed: S::Signed) -> S::Signed
+    where S: FullScale,
+{
+    if signed == S::MIN { S::MAX } else { -signed }
+}
+
+/// Move the sample `l` towards `m` by the given gain, returning `m + (l - m) * gain`.
+///
+/// The blend is calculated at the precision of the gain type `G` regardless of the sample format.
+fn blend<S, G>(l: S, m: S, gain: G) -> S
+    where S: Sample,
+          G: FloatSample,
+{
+    let l_g: G = l.to_float_sample().to_sample::<f64>().to_sample();
+    let m_g: G = m.to_float_sample().to_sample::<f64>().to_sample();
+    (m_g + (l_g - m_g) * gain).to_sample::<f64>().to_sample::<S::Float>().to_sample()
+}
+
+/// The same as **blend**, correcting for the truncation of integer sample formats.
+///
+/// Floating point formats are blended directly. For integer formats the result is clamped so
+/// that it never passes `m`, as truncation could otherwise leave a releasing envelope below (or
+/// an attacking envelope above) the detected frame and flip the choice of gain on the following
+/// frame. The truncation would also stall the envelope a few steps short of `m` once the
+/// remaining distance multiplied by `1 - gain` falls below a single step. In this case the
+/// envelope is moved a single step towards `m` instead, so that it settles exactly on a steady
+/// level.
+fn apply_gain<S, G>(l: S, m: S, gain: G) -> S
+    where S: FullScale,
+          G: FloatSample,
+{
+    let env = blend(l, m, gain);
+    if S::STEP == 0.0 {
+        return env;
+    }
+    let (lo, hi) = if l < m { (l, m) } else { (m, l) };
+    let env = if env < lo { lo } else if env > hi { hi } else { env };
+    if env == l && l != m && gain < <G as FloatSample>::identity() {
+        let l_f = l.to_float_sample().to_sample::<f64>();
+        let stepped = if l < m { l_f + S::STEP } else { l_f - S::STEP };
+        return stepped.to_sample::<S::Float>().to_sample();
+    }
+    env
+}
+
+/// Treat negative and NaN frame counts as `0.0` (an instantaneous attack or release).
+fn valid_frames(frames: f64) -> f64 {
+    if frames > 0.0 { frames } else { 0.0 }
+}
+
+/// Rescale the exact number of frames `n_frames` by the given ratio.
+fn retune_frames(n_frames: f64, ratio: f64) -> f64 {
+    n_frames * ratio
+}
+
+/// Convert a slew rate in decibels per second to decibels per frame, treating negative and NaN
+/// rates as `0.0`.
+fn slew_db_per_frame(db_per_sec: f32, sample_hz: f64) -> f64 {
+    let db_per_sec = if db_per_sec > 0.0 { db_per_sec as f64 } else { 0.0 };
+    db_per_sec / sample_hz
+}
+
+/// Move the sample `l` towards `m` by at most `amp_per_frame`, where full scale is `1.0`.
+///
+/// The ramp is calculated at `f64` precision so that constant rates hold over many frames.
+fn apply_linear<S>(l: S, m: S, amp_per_frame: f64) -> S
+    where S: Sample,
+{
+    let l_f = l.to_float_sample().to_sample::<f64>();
+    let m_f = m.to_float_sample().to_sample::<f64>();
+    let env = if m_f > l_f { l_f + amp_per_frame } else { l_f - amp_per_frame };
+    // Once the detected level is within reach, the envelope lands on it exactly.
+    if (m_f > l_f && env >= m_f) || (m_f <= l_f && env <= m_f) {
+        return m;
+    }
+    env.to_sample::<S::Float>().to_sample()
+}
 
-fn calc_gain(n_frames: f32) -> f32 {
-    ::std::f32::consts::E.powf(-1.0 / n_frames)
+/// Move the sample `l` towards `m` by at most `db_per_frame` decibels, retaining the sign of `m`.
+///
+/// Levels at or below **LOG_DOMAIN_FLOOR_DB** are treated as silence. The slew is calculated at
+/// `f64` precision so that constant rates hold over many frames.
+fn apply_slew<S>(l: S, m: S, db_per_frame: f64) -> S
+    where S: Sample,
+{
+    let floor_db = LOG_DOMAIN_FLOOR_DB as f64;
+    let to_db = |amp: f64| {
+        if amp <= 0.0 { return floor_db; }
+        let db = 20.0 * amp.ln() / std::f64::consts::LN_10;
+        if db < floor_db { floor_db } else { db }
+    };
+    let l_f = l.to_float_sample().to_sample::<f64>();
+    let m_f = m.to_float_sample().to_sample::<f64>();
+    let l_db = to_db(if l_f < 0.0 { -l_f } else { l_f });
+    let m_db = to_db(if m_f < 0.0 { -m_f } else { m_f });
+    let env_db = if m_db > l_db { l_db + db_per_frame } else { l_db - db_per_frame };
+    // Once the detected level is within reach, the envelope lands on it exactly.
+    if (m_db > l_db && env_db >= m_db) || (m_db <= l_db && env_db <= m_db) {
+        return m;
+    }
+    let env = if env_db <= floor_db {
+        0.0
+    } else {
+        (env_db / 20.0 * std::f64::consts::LN_10).exp()
+    };
+    // Retain which side of equilibrium the envelope is on.
+    let env = if m_f < 0.0 || (m_f == 0.0 && l_f < 0.0) { -env } else { env };
+    env.to_sample::<S::Float>().to_sample()
 }
 
 
 impl<F> EnvelopeDetector<F, Rms<F>>
     where F: Frame,
+          F::Sample: FullScale,
 {
 
     /// Construct a new **Rms** **EnvelopeDetector**.
+    ///
+    /// ```
+    /// use envelope_detector::EnvelopeDetector;
+    ///
+    /// // A 10ms window with a 1ms attack and release at 44.1kHz, as in `examples/test.rs`.
+    /// let mut detector = EnvelopeDetector::rms(441, 44.1, 44.1);
+    /// for _ in 0..4410 {
+    ///     detector.next([0.5f32, -0.5]);
+    /// }
+    /// let env = detector.next([0.5, -0.5]);
+    /// assert!((env[0] - 0.5).abs() < 1e-3 && (env[1] - 0.5).abs() < 1e-3);
+    /// ```
     pub fn rms(rms_window_frames: usize, attack_frames: f32, release_frames: f32) -> Self {
         let rms = Rms::new(rms_window_frames);
         Self::new(rms, attack_frames, release_frames)
     }
 
+    /// Construct a new **Rms** **EnvelopeDetector**, validating the given parameters.
+    ///
+    /// Unlike `rms` (which accepts a zero length window, yielding an envelope that remains at
+    /// equilibrium), this returns an **EnvelopeError** if the window is empty or if either the
+    /// attack or release time is infinite or NaN. This is useful for constructing a detector from
+    /// user input.
+    pub fn try_rms(rms_window_frames: usize,
+                   attack_frames: f32,
+                   release_frames: f32) -> Result<Self, EnvelopeError>
+    {
+        if rms_window_frames == 0 {
+            return Err(EnvelopeError::ZeroWindow);
+        }
+        if !attack_frames.is_finite() {
+            return Err(EnvelopeError::NonFiniteAttack);
+        }
+        if !release_frames.is_finite() {
+            return Err(EnvelopeError::NonFiniteRelease);
+        }
+        Ok(Self::rms(rms_window_frames, attack_frames, release_frames))
+    }
+
+    /// Construct a new **Rms** **EnvelopeDetector** with the window, attack and release times
+    /// given as durations at the given sample rate.
+    pub fn rms_from_durations(rms_window: Duration,
+                              attack: Duration,
+                              release: Duration,
+                              sample_hz: f64) -> Self
+    {
+        let rms_window_frames = duration_frames(rms_window, sample_hz).round() as usize;
+        let attack_frames = duration_frames(attack, sample_hz) as f32;
+        let release_frames = duration_frames(release, sample_hz) as f32;
+        Self::rms(rms_window_frames, attack_frames, release_frames)
+    }
+
+    /// Construct a new **Rms** **EnvelopeDetector** with VU meter style ballistics at the given
+    /// sample rate.
+    ///
+    /// The RMS is integrated over a 300 ms window, while the attack and release are
+    /// instantaneous, so that a step input reaches its full level after 300 ms.
+    pub fn vu(sample_hz: f64) -> Self {
+        let rms_window_frames = (0.3 * sample_hz).round() as usize;
+        Self::rms(rms_window_frames, 0.0, 0.0)
+    }
+
     /// Set the duration of the **Rms** window in frames.
     pub fn set_window_frames(&mut self, n_window_frames: usize) {
         self.mode.set_window_frames(n_window_frames);
     }
 
+    /// Set the duration of the **Rms** window in milliseconds at the given sample rate.
+    ///
+    /// The duration is rounded to the nearest whole frame. The running sum of the window remains
+    /// consistent with its contents across the resize (see **Rms::set_window_frames**).
+    pub fn set_window_ms(&mut self, ms: f64, sample_hz: f64) {
+        let n_window_frames = ms_frames(ms, sample_hz).round() as usize;
+        self.set_window_frames(n_window_frames);
+    }
+
 }
 
-impl<F> EnvelopeDetector<F, Peak<peak::FullWave>>
+impl<F, const N: usize> EnvelopeDetector<F, ArrayRms<F, N>>
     where F: Frame,
+          F::Sample: FullScale,
 {
 
-    /// Construct a new **Mono** **Peak** **EnvelopeDetector**.
-    pub fn peak(attack_frames: f32, release_frames: f32) -> Self {
-        let peak = Peak::full_wave();
-        Self::new(peak, attack_frames, release_frames)
+    /// Construct a new **ArrayRms** **EnvelopeDetector** with an RMS window of `N` frames, where
+    /// `N` is typically given via the alias, i.e.
+    /// `let detector: ArrayRmsEnvelopeDetector<[f32; 2], 128> = EnvelopeDetector::array_rms(..)`.
+    ///
+    /// Unlike `rms`, this requires no allocation.
+    pub fn array_rms(attack_frames: f32, release_frames: f32) -> Self {
+        Self::new(Rms::with_array(), attack_frames, release_frames)
     }
 
 }
 
-impl<F, M> EnvelopeDetector<F, M>
+impl<F> EnvelopeDetector<F, ExpRms<F>>
     where F: Frame,
-          M: Mode<F>,
+          F::Sample: FullScale,
 {
 
-    fn new(mode: M, attack_frames: f32, release_frames: f32) -> Self {
-        EnvelopeDetector {
-            mode: mode,
-            last_env_frame: F::equilibrium(),
-            attack_gain: calc_gain(attack_frames),
-            release_gain: calc_gain(release_frames),
-        }
+    /// Construct a new **ExpRms** **EnvelopeDetector**.
+    ///
+    /// The RMS averaging time constant is given as a number of frames.
+    pub fn exp_rms(rms_window_frames: f32, attack_frames: f32, release_frames: f32) -> Self {
+        let exp_rms = ExpRms::new(rms_window_frames);
+        Self::new(exp_rms, attack_frames, release_frames)
     }
 
-    /// Set the **EnvelopeDetector**'s attack time as a number of frames.
-    pub fn set_attack_frames(&mut self, frames: f32) {
-        self.attack_gain = calc_gain(frames);
+}
+
+impl<F> EnvelopeDetector<F, Peak<peak::FullWave>>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **Mono** **Peak** **EnvelopeDetector**.
+    pub fn peak(attack_frames: f32, release_frames: f32) -> Self {
+        let peak = Peak::full_wave();
+        Self::new(peak, attack_frames, release_frames)
     }
 
-    /// Set the **EnvelopeDetector**'s release time as a number of frames.
-    pub fn set_release_frames(&mut self, frames: f32) {
-        self.attack_gain = calc_gain(frames);
+    /// Construct a new **Peak** **EnvelopeDetector** with an instantaneous attack.
+    ///
+    /// The envelope jumps to each new peak immediately and only the decay is smoothed by the
+    /// release, as is common for simple amplitude followers and meters.
+    pub fn peak_instant_attack(release_frames: f32) -> Self {
+        Self::peak(0.0, release_frames)
     }
 
-    /// Given the next input signal frame, detect and return the next envelope frame.
-    pub fn next(&mut self, frame: F) -> F {
-        let EnvelopeDetector {
-            attack_gain, release_gain, ref mut mode, ref mut last_env_frame,
-        } = *self;
-
-        let mode_frame = mode.next_frame(frame);
-        let new_env_frame = last_env_frame.zip_map(mode_frame, |l, m| {
-            let gain = if l < m { attack_gain } else { release_gain };
-            let diff = l.add_amp(-m.to_signed_sample());
-            m.add_amp(diff.mul_amp(gain.to_sample()).to_sample())
-        });
-        *last_env_frame = new_env_frame;
-        new_env_frame
+    /// Construct a new **Peak** **EnvelopeDetector** with the attack and release times given as
+    /// durations at the given sample rate.
+    pub fn peak_from_durations(attack: Duration, release: Duration, sample_hz: f64) -> Self {
+        let attack_frames = duration_frames(attack, sample_hz) as f32;
+        let release_frames = duration_frames(release, sample_hz) as f32;
+        Self::peak(attack_frames, release_frames)
     }
 
-    /// Given the next input signal frame, detect and return the next envelope average across each
-    /// channel for the frame.
+    /// Construct a new **Peak** **EnvelopeDetector** with PPM (peak programme meter) style
+    /// ballistics at the given sample rate.
     ///
-    /// The returned value will take the form of the signal's equivalent floating point sample
-    /// format.
-    pub fn next_avg(&mut self, frame: F) -> <F::Sample as Sample>::Float {
-        let next_frame = self.next(frame);
-        let equilibrium: F::Sample = Sample::equilibrium();
-        let sum = next_frame.channels().fold(equilibrium, |sum, s| sum.add_amp(s.to_sample()));
-        sum.to_float_sample() / (F::n_channels() as f32).to_sample()
+    /// The attack time constant is 10 ms and the release (fallback) time constant is 1.5 s.
+    pub fn ppm(sample_hz: f64) -> Self {
+        let attack_frames = time_constant_frames(0.010, sample_hz);
+        let release_frames = time_constant_frames(1.5, sample_hz);
+        Self::peak(attack_frames, release_frames)
     }
 
 }
+
+impl<F> EnvelopeDetector<F, PeakHold<F>>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **PeakHold** **EnvelopeDetector**.
+    ///
+    /// Each new peak is held for `hold_frames` before the release takes over.
+    pub fn peak_hold(hold_frames: usize, attack_frames: f32, release_frames: f32) -> Self {
+        let peak_hold = PeakHold::new(hold_frames);
+        Self::new(peak_hold, attack_frames, release_frames)
+    }
+
+}
+
+impl<F> EnvelopeDetector<F, AnyMode<F>>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Switch to the given detection **Mode** mid-stream without losing the envelope state.
+    ///
+    /// The attack and release gains and the current envelope are retained, so the envelope
+    /// continues from its current value rather than jumping to equilibrium. When switching to a
+    /// windowed mode (**Rms** or **ExpRms**), the new mode is seeded as though it had been
+    /// receiving a steady signal at the level of the current envelope, so that it does not ramp up
+    /// from silence. Stateless modes (i.e. **Peak**) simply continue following the signal from
+    /// the current envelope.
+    pub fn set_mode(&mut self, mode: AnyMode<F>) {
+        self.mode = mode;
+        let envelope = self.current();
+        self.mode.seed(envelope);
+    }
+
+}
+
+impl<F> Default for EnvelopeDetector<F, Peak<peak::FullWave>>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    /// A full wave **Peak** **EnvelopeDetector** with a 1 frame attack and 100 frame release.
+    fn default() -> Self {
+        Self::peak(1.0, 100.0)
+    }
+}
+
+impl<F, M> EnvelopeDetector<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// Construct a new **EnvelopeDetector** from the given detection **Mode**.
+    ///
+    /// This allows for constructing a detector over any **Mode**, including **AnyMode** for
+    /// detectors whose mode may be switched at runtime.
+    pub fn new(mode: M, attack_frames: f32, release_frames: f32) -> Self {
+        Self::with_ballistics(mode, attack_frames, release_frames, Ballistics::Digital)
+    }
+
+    /// Construct a new **EnvelopeDetector** from the given detection **Mode**, converting the
+    /// attack and release times to gains using the given **Ballistics**.
+    pub fn with_ballistics(mode: M,
+                           attack_frames: f32,
+                           release_frames: f32,
+                           ballistics: Ballistics) -> Self
+    {
+        EnvelopeDetector {
+            mode,
+            last_env_frame: Frame::equilibrium(),
+            age: 0,
+            last_phase: Phase::Steady,
+            session_peak: F::equilibrium(),
+            attack_smoothing: Smoothing::OnePole,
+            release_smoothing: Smoothing::OnePole,
+            attack_gain: ballistics.gain(attack_frames as f64).to_sample(),
+            release_gain: ballistics.gain(release_frames as f64).to_sample(),
+            attack_frames: valid_frames(attack_frames as f64),
+            release_frames: valid_frames(release_frames as f64),
+            attack_per_channel: None,
+            release_per_channel: None,
+            ballistics,
+            oversample_factor: 1.0,
+        }
+    }
+
+    /// Construct a new **EnvelopeDetector** from the given detection **Mode** whose attack and
+    /// release each follow the given **Smoothing**, i.e. a fast one-pole attack combined with a
+    /// linear release.
+    ///
+    /// The attack and release times give the length of each stage in frames. The smoothing is
+    /// applied by `next` and the methods built upon it, but not by `next_branchless` or
+    /// `next_log_domain`.
+    pub fn with_smoothing(mode: M,
+                          attack_frames: f32,
+                          release_frames: f32,
+                          attack_smoothing: Smoothing,
+                          release_smoothing: Smoothing) -> Self
+    {
+        let mut detector = Self::new(mode, attack_frames, release_frames);
+        detector.attack_smoothing = attack_smoothing;
+        detector.release_smoothing = release_smoothing;
+        detector
+    }
+
+    /// Construct a new **EnvelopeDetector** from the given detection **Mode** with a separate
+    /// attack and release time (as a number of frames) for each channel, i.e. for a set of stems
+    /// that each require their own ballistics.
+    ///
+    /// See `set_attack_frames_per_channel` and `set_release_frames_per_channel`.
+    pub fn with_frames_per_channel(mode: M, attack_frames: F::Float, release_frames: F::Float)
+        -> Self
+    {
+        let mut detector = Self::new(mode, 0.0, 0.0);
+        detector.set_attack_frames_per_channel(attack_frames);
+        detector.set_release_frames_per_channel(release_frames);
+        detector
+    }
+
+    /// Consume the **EnvelopeDetector** and return an iterator that lazily yields the envelope of
+    /// each frame yielded by the given signal.
+    ///
+    /// The returned **Envelope** is itself a `sample::Signal`.
+    pub fn envelope<S>(self, signal: S) -> Envelope<S, F, M>
+        where S: Iterator<Item=F>,
+    {
+        Envelope::new(signal, self)
+    }
+
+    /// Consume the **EnvelopeDetector** and return an iterator that lazily yields each frame of
+    /// the given signal paired with its envelope, as `(input, envelope)`.
+    pub fn envelope_zip<I>(self, signal: I) -> EnvelopeZip<I, F, M>
+        where I: Iterator<Item=F>,
+    {
+        EnvelopeZip::new(signal, self)
+    }
+
+}
+
+impl<F, M, G> EnvelopeDetector<F, M, G>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+          G: FloatSample,
+{
+
+    /// Convert the **EnvelopeDetector** to one that stores and applies its attack and release
+    /// gains at the precision of the floating point type `H`, i.e.
+    /// `EnvelopeDetector::peak(10.0, 441_000.0).with_gain_type::<f64>()`.
+    ///
+    /// By default the gains are `f32`. `f64` gains allow a pipeline of `f64` frames to retain full
+    /// precision end-to-end. The gains are recalculated from the attack and release times, while
+    /// the envelope and the state of the detection **Mode** are retained.
+    pub fn with_gain_type<H>(self) -> EnvelopeDetector<F, M, H>
+        where H: FloatSample,
+    {
+        let mut detector = EnvelopeDetector {
+            attack_gain: Sample::equilibrium(),
+            release_gain: Sample::equilibrium(),
+            attack_frames: self.attack_frames,
+            release_frames: self.release_frames,
+            attack_per_channel: self.attack_per_channel,
+            release_per_channel: self.release_per_channel,
+            ballistics: self.ballistics,
+            oversample_factor: self.oversample_factor,
+            last_env_frame: self.last_env_frame,
+            age: self.age,
+            last_phase: self.last_phase,
+            session_peak: self.session_peak,
+            attack_smoothing: self.attack_smoothing,
+            release_smoothing: self.release_smoothing,
+            mode: self.mode,
+        };
+        detector.calc_gains();
+        detector
+    }
+
+    /// The factor by which the attack and release frame counts are scaled when converted to
+    /// gains.
+    pub fn oversample_factor(&self) -> f32 {
+        self.oversample_factor
+    }
+
+    /// Set the factor at which the detector runs above the base sample rate, i.e. `4.0` when
+    /// running within a 4x oversampled region.
+    ///
+    /// The attack and release times continue to be specified (and reported) as a number of frames
+    /// at the base rate, and are scaled by the factor when converted to gains so that they remain
+    /// correct at the internal rate. The gains are recalculated from the current attack and
+    /// release times.
+    ///
+    /// Factors of `0.0` or less (or NaN) are treated as `1.0`.
+    pub fn set_oversample_factor(&mut self, oversample_factor: f32) {
+        self.oversample_factor = if oversample_factor > 0.0 { oversample_factor } else { 1.0 };
+        self.calc_gains();
+    }
+
+    /// The **Ballistics** used to convert the attack and release times to gains.
+    pub fn ballistics(&self) -> Ballistics {
+        self.ballistics
+    }
+
+    /// Change the **Ballistics** used to convert the attack and release times to gains.
+    ///
+    /// The gains are recalculated from the current attack and release times.
+    pub fn set_ballistics(&mut self, ballistics: Ballistics) {
+        self.ballistics = ballistics;
+        self.calc_gains();
+    }
+
+    /// Recalculate the attack and release gains from the current attack and release times.
+    fn calc_gains(&mut self) {
+        let oversample_factor = self.oversample_factor as f64;
+        self.attack_gain = self.ballistics.gain(self.attack_frames * oversample_factor).to_sample();
+        self.release_gain = self.ballistics.gain(self.release_frames * oversample_factor)
+            .to_sample();
+        if let Some(times) = self.attack_per_channel {
+            self.attack_per_channel = Some(self.channel_times(times.frames, times.scale));
+        }
+        if let Some(times) = self.release_per_channel {
+            self.release_per_channel = Some(self.channel_times(times.frames, times.scale));
+        }
+    }
+
+    /// Rescale the attack, release and slew rates along with any durations held by the **Mode**
+    /// following a change in sample rate, where `ratio` is the new sample rate divided by the old.
+    ///
+    /// All durations are rescaled at `f64` precision from their exact values rather than from
+    /// their rounded ones, so that small ratios are not lost and repeated calls do not drift.
+    pub(crate) fn retune(&mut self, ratio: f64) {
+        self.attack_frames = retune_frames(self.attack_frames, ratio);
+        self.release_frames = retune_frames(self.release_frames, ratio);
+        for times in self.attack_per_channel.iter_mut().chain(self.release_per_channel.iter_mut()) {
+            times.scale = retune_frames(times.scale, ratio);
+        }
+        self.attack_smoothing = self.attack_smoothing.retune(ratio);
+        self.release_smoothing = self.release_smoothing.retune(ratio);
+        self.calc_gains();
+        self.mode.retune(ratio);
+    }
+
+    /// The given per-channel frame counts, rescaled by `scale`, along with the gains calculated
+    /// from them.
+    fn channel_times(&self, frames: F::Float, scale: f64) -> ChannelTimes<F::Float> {
+        let ballistics = self.ballistics;
+        let oversample_factor = self.oversample_factor as f64;
+        ChannelTimes {
+            frames,
+            scale,
+            gains: frames.map(|n| {
+                let n = retune_frames(n.to_sample::<f64>(), scale);
+                ballistics.gain(n * oversample_factor).to_sample()
+            }),
+        }
+    }
+
+    /// Map each channel of the current envelope and the detected frame, smoothed in the **Domain**
+    /// `D`, to the new envelope via `apply` given the attack and release gains of the channel.
+    ///
+    /// Whether the attack and/or release times differ per channel is checked once for the whole
+    /// frame, so that the channel loop does not walk the per-channel tables of the common case
+    /// where neither does.
+    fn zip_map_gains<D, A>(&self, mode_frame: D::Frame, mut apply: A) -> D::Frame
+        where D: Domain<F>,
+              A: FnMut(D::Sample, D::Sample, G, G) -> D::Sample,
+    {
+        let env_frame = D::from_float(self.last_env_frame);
+        // A mode that applies its own release is followed as soon as it falls.
+        let (release_gain, release_per_channel) = if self.mode.applies_release() {
+            (G::equilibrium(), None)
+        } else {
+            (self.release_gain, self.release_per_channel)
+        };
+        let attack_gain = self.attack_gain;
+        match (self.attack_per_channel, release_per_channel) {
+            (None, None) => {
+                env_frame.zip_map(mode_frame, |l, m| apply(l, m, attack_gain, release_gain))
+            },
+            (Some(attack), None) => {
+                let mut attack = attack.gains.channels();
+                env_frame.zip_map(mode_frame, |l, m| {
+                    apply(l, m, channel_gain(attack.next(), attack_gain), release_gain)
+                })
+            },
+            (None, Some(release)) => {
+                let mut release = release.gains.channels();
+                env_frame.zip_map(mode_frame, |l, m| {
+                    apply(l, m, attack_gain, channel_gain(release.next(), release_gain))
+                })
+            },
+            (Some(attack), Some(release)) => {
+                let mut gains = attack.gains.channels().zip(release.gains.channels());
+                env_frame.zip_map(mode_frame, |l, m| {
+                    let (attack, release) = gains.next().unzip();
+                    let attack_gain = channel_gain(attack, attack_gain);
+                    apply(l, m, attack_gain, channel_gain(release, release_gain))
+                })
+            },
+        }
+    }
+
+    /// Set the **EnvelopeDetector**'s attack time as a number of frames.
+    ///
+    /// Negative and NaN frame counts are treated as `0.0` (an instantaneous attack). If the attack
+    /// was slew-limited, the exponential attack is restored.
+    pub fn set_attack_frames(&mut self, frames: f32) {
+        self.set_attack_frames_f64(frames as f64);
+    }
+
+    /// Set the **EnvelopeDetector**'s attack time as a number of frames at `f64` precision, i.e.
+    /// when the time has been converted from milliseconds at some sample rate.
+    ///
+    /// See `set_attack_frames`.
+    pub fn set_attack_frames_f64(&mut self, frames: f64) {
+        let frames = valid_frames(frames);
+        self.attack_gain = self.ballistics.gain(frames * self.oversample_factor as f64)
+            .to_sample();
+        self.attack_frames = frames;
+        self.attack_per_channel = None;
+        if let Smoothing::DbPerFrame(_) = self.attack_smoothing {
+            self.attack_smoothing = Smoothing::OnePole;
+        }
+    }
+
+    /// Set the **EnvelopeDetector**'s release time as a number of frames.
+    ///
+    /// Negative and NaN frame counts are treated as `0.0` (an instantaneous release). If the
+    /// release was slew-limited, the exponential release is restored.
+    pub fn set_release_frames(&mut self, frames: f32) {
+        self.set_release_frames_f64(frames as f64);
+    }
+
+    /// Set the **EnvelopeDetector**'s release time as a number of frames at `f64` precision, i.e.
+    /// when the time has been converted from milliseconds at some sample rate.
+    ///
+    /// See `set_release_frames`.
+    pub fn set_release_frames_f64(&mut self, frames: f64) {
+        let frames = valid_frames(frames);
+        self.release_gain = self.ballistics.gain(frames * self.oversample_factor as f64)
+            .to_sample();
+        self.release_frames = frames;
+        self.release_per_channel = None;
+        if let Smoothing::DbPerFrame(_) = self.release_smoothing {
+            self.release_smoothing = Smoothing::OnePole;
+        }
+    }
+
+    /// Set a separate attack time as a number of frames for each channel.
+    ///
+    /// The per-channel gains are stored at the precision of `F::Float` rather than at the precision
+    /// of the gain type. While set, `attack_frames` and `attack_gain` report the time and gain of
+    /// the first channel. Calling `set_attack_frames` restores a single attack time shared by all
+    /// channels.
+    ///
+    /// Negative and NaN frame counts are treated as `0.0` (an instantaneous attack). If the attack
+    /// was slew-limited, the exponential attack is restored.
+    pub fn set_attack_frames_per_channel(&mut self, frames: F::Float) {
+        let first = frames.channels().next().map_or(0.0, |n| n.to_sample::<f32>());
+        self.set_attack_frames(first);
+        self.attack_per_channel = Some(self.channel_times(frames, 1.0));
+    }
+
+    /// Set a separate release time as a number of frames for each channel.
+    ///
+    /// The per-channel gains are stored at the precision of `F::Float` rather than at the precision
+    /// of the gain type. While set, `release_frames` and `release_gain` report the time and gain of
+    /// the first channel. Calling `set_release_frames` restores a single release time shared by all
+    /// channels.
+    ///
+    /// Negative and NaN frame counts are treated as `0.0` (an instantaneous release). If the
+    /// release was slew-limited, the exponential release is restored.
+    pub fn set_release_frames_per_channel(&mut self, frames: F::Float) {
+        let first = frames.channels().next().map_or(0.0, |n| n.to_sample::<f32>());
+        self.set_release_frames(first);
+        self.release_per_channel = Some(self.channel_times(frames, 1.0));
+    }
+
+    /// The attack time of the given channel as a number of frames.
+    ///
+    /// Unless set via `set_attack_frames_per_channel`, every channel has the same attack time as
+    /// reported by `attack_frames`.
+    ///
+    /// **Panics** if `channel` is not less than the number of channels in `F`.
+    pub fn channel_attack_frames(&self, channel: usize) -> f32 {
+        assert!(channel < F::n_channels(), "the channel index ({}) must be less than the number \
+                 of channels ({})", channel, F::n_channels());
+        match self.attack_per_channel {
+            Some(times) => {
+                let frames = times.frames.channel(channel).unwrap().to_sample::<f64>();
+                retune_frames(frames, times.scale) as f32
+            },
+            None => self.attack_frames(),
+        }
+    }
+
+    /// The release time of the given channel as a number of frames.
+    ///
+    /// Unless set via `set_release_frames_per_channel`, every channel has the same release time
+    /// as reported by `release_frames`.
+    ///
+    /// **Panics** if `channel` is not less than the number of channels in `F`.
+    pub fn channel_release_frames(&self, channel: usize) -> f32 {
+        assert!(channel < F::n_channels(), "the channel index ({}) must be less than the number \
+                 of channels ({})", channel, F::n_channels());
+        match self.release_per_channel {
+            Some(times) => {
+                let frames = times.frames.channel(channel).unwrap().to_sample::<f64>();
+                retune_frames(frames, times.scale) as f32
+            },
+            None => self.release_frames(),
+        }
+    }
+
+    /// Replace the exponential attack with a slew-limited attack that rises at a constant rate
+    /// in decibels per second for a signal at the given sample rate, i.e. as in a meter
+    /// specified as "attack at 200 dB/s".
+    ///
+    /// Rather than moving a fraction of the way towards the detected level each frame, the
+    /// envelope rises linearly in dB until it reaches the detected level. The rise starts from
+    /// [**LOG_DOMAIN_FLOOR_DB**](./constant.LOG_DOMAIN_FLOOR_DB) when the envelope is silent.
+    /// Negative and NaN rates are treated as `0.0`, in which case the envelope never rises.
+    ///
+    /// The slew is applied by `next` and the methods built upon it, but not by `next_branchless`
+    /// or `next_log_domain`. The exponential attack may be restored via `set_attack_frames`.
+    ///
+    /// This sets the attack **Smoothing** to **Smoothing::DbPerFrame**.
+    pub fn set_attack_slew_db_per_sec(&mut self, db_per_sec: f32, sample_hz: f64) {
+        let db_per_frame = slew_db_per_frame(db_per_sec, sample_hz);
+        self.attack_smoothing = Smoothing::DbPerFrame(db_per_frame);
+    }
+
+    /// Replace the exponential release with a slew-limited release that falls at a constant rate
+    /// in decibels per second for a signal at the given sample rate, i.e. as in a meter
+    /// specified as "release at 20 dB/s".
+    ///
+    /// Rather than moving a fraction of the way towards the detected level each frame, the
+    /// envelope falls linearly in dB until it reaches the detected level, treating levels at or
+    /// below [**LOG_DOMAIN_FLOOR_DB**](./constant.LOG_DOMAIN_FLOOR_DB) as silence. Negative and
+    /// NaN rates are treated as `0.0`, in which case the envelope never falls.
+    ///
+    /// The slew is applied by `next` and the methods built upon it, but not by `next_branchless`
+    /// or `next_log_domain`. The exponential release may be restored via `set_release_frames`.
+    ///
+    /// This sets the release **Smoothing** to **Smoothing::DbPerFrame**.
+    pub fn set_release_slew_db_per_sec(&mut self, db_per_sec: f32, sample_hz: f64) {
+        let db_per_frame = slew_db_per_frame(db_per_sec, sample_hz);
+        self.release_smoothing = Smoothing::DbPerFrame(db_per_frame);
+    }
+
+    /// The **Smoothing** applied by the attack.
+    pub fn attack_smoothing(&self) -> Smoothing {
+        self.attack_smoothing
+    }
+
+    /// The **Smoothing** applied by the release.
+    pub fn release_smoothing(&self) -> Smoothing {
+        self.release_smoothing
+    }
+
+    /// The maximum rise in decibels per frame if the attack is slew-limited.
+    pub fn attack_slew_db_per_frame(&self) -> Option<f32> {
+        self.attack_smoothing.db_per_frame()
+    }
+
+    /// The maximum fall in decibels per frame if the release is slew-limited.
+    pub fn release_slew_db_per_frame(&self) -> Option<f32> {
+        self.release_smoothing.db_per_frame()
+    }
+
+    /// Set both the attack and release times as a number of frames in a single call.
+    ///
+    /// This is equivalent to calling `set_attack_frames` followed by `set_release_frames`, and is
+    /// convenient when switching between ballistics presets. Negative and NaN frame counts are
+    /// treated as `0.0`.
+    pub fn set_attack_release_frames(&mut self, attack_frames: f32, release_frames: f32) {
+        self.set_attack_frames(attack_frames);
+        self.set_release_frames(release_frames);
+    }
+
+    /// Set the **EnvelopeDetector**'s attack time from a time constant in seconds.
+    ///
+    /// See [**time_constant_frames**](./fn.time_constant_frames).
+    pub fn set_attack_time_constant(&mut self, secs: f64, sample_hz: f64) {
+        self.set_attack_frames(time_constant_frames(secs, sample_hz));
+    }
+
+    /// Set the **EnvelopeDetector**'s release time from a time constant in seconds.
+    ///
+    /// See [**time_constant_frames**](./fn.time_constant_frames).
+    pub fn set_release_time_constant(&mut self, secs: f64, sample_hz: f64) {
+        self.set_release_frames(time_constant_frames(secs, sample_hz));
+    }
+
+    /// A reference to the detection **Mode**.
+    pub fn mode(&self) -> &M {
+        &self.mode
+    }
+
+    /// A mutable reference to the detection **Mode**.
+    ///
+    /// This may be used to reconfigure the mode or, in the case of **AnyMode**, to switch modes
+    /// mid-stream.
+    pub fn mode_mut(&mut self) -> &mut M {
+        &mut self.mode
+    }
+
+    /// Reset the envelope to equilibrium along with any state held by the detection **Mode**
+    /// (i.e. the **Rms** window and its running sum).
+    ///
+    /// The `session_peak` is left intact, see `clear_session_peak`.
+    pub fn reset(&mut self) {
+        self.last_env_frame = Frame::equilibrium();
+        self.age = 0;
+        self.last_phase = Phase::Steady;
+        self.mode.reset();
+    }
+
+    /// Overwrite the current envelope with the given frame.
+    ///
+    /// This is useful for priming the envelope with the tail of some previous block in order to
+    /// avoid a discontinuity. Unlike `reset`, the state of the detection **Mode** is left intact.
+    pub fn reset_to(&mut self, frame: F) {
+        self.last_env_frame = frame.to_float_frame();
+    }
+
+    /// A clone of the **EnvelopeDetector** that shares its configuration (the attack and release,
+    /// the **Mode** parameters such as the **Rms** window length, etc) but starts from a fresh
+    /// state, i.e. for spawning per-voice detectors from a template.
+    ///
+    /// Unlike `clone`, the envelope, the state of the detection **Mode** and the `session_peak`
+    /// of the result are all at equilibrium, as for a newly constructed detector.
+    pub fn clone_reset(&self) -> Self
+        where M: Clone,
+    {
+        let mut detector = self.clone();
+        detector.reset();
+        detector.clear_session_peak();
+        detector
+    }
+
+    /// The **EnvelopeDetector**'s attack time as a number of frames (at the base rate, see
+    /// `set_oversample_factor`).
+    ///
+    /// This is the time exactly as it was last set, with negative and NaN times reported as `0.0`.
+    pub fn attack_frames(&self) -> f32 {
+        self.attack_frames as f32
+    }
+
+    /// The **EnvelopeDetector**'s release time as a number of frames (at the base rate, see
+    /// `set_oversample_factor`).
+    ///
+    /// This is the time exactly as it was last set, with negative and NaN times reported as `0.0`.
+    pub fn release_frames(&self) -> f32 {
+        self.release_frames as f32
+    }
+
+    /// The one-pole coefficient applied while the envelope is rising, as calculated from the
+    /// attack time.
+    ///
+    /// The detector applies the gain at the precision of the gain type `G` (`f32` by default);
+    /// the coefficient returned here is rounded to `f32`. With **Ballistics::Digital** this is
+    /// `gain_from_frames(self.attack_frames())`.
+    pub fn attack_gain(&self) -> f32 {
+        self.attack_gain.to_sample()
+    }
+
+    /// The one-pole coefficient applied while the envelope is falling, as calculated from the
+    /// release time.
+    ///
+    /// The detector applies the gain at the precision of the gain type `G` (`f32` by default);
+    /// the coefficient returned here is rounded to `f32`. With **Ballistics::Digital** this is
+    /// `gain_from_frames(self.release_frames())`.
+    pub fn release_gain(&self) -> f32 {
+        self.release_gain.to_sample()
+    }
+
+    /// Whether the most recent frame attacked, released or held the envelope steady.
+    ///
+    /// The phase is updated by `next`, `next_in_place` and the methods built upon them (i.e.
+    /// `next_with_phase`), but not by `next_branchless`, `next_log_domain` or `next_db_decay`.
+    /// It is **Phase::Steady** following a `reset`.
+    pub fn last_phase(&self) -> Phase {
+        self.last_phase
+    }
+
+    /// The envelope of greatest magnitude in each channel since the **EnvelopeDetector** was
+    /// constructed or `clear_session_peak` was last called, i.e. for a "max" indicator on a meter.
+    ///
+    /// Unlike the envelope itself this never decays, and is left intact by `reset`. The session
+    /// peak is updated by every method that detects a new envelope frame, including
+    /// `next_branchless`, `next_log_domain` and `next_db_decay`, at the cost of one comparison per
+    /// channel per frame.
+    pub fn session_peak(&self) -> F {
+        self.session_peak
+    }
+
+    /// Clear the `session_peak` back to equilibrium.
+    pub fn clear_session_peak(&mut self) {
+        self.session_peak = F::equilibrium();
+    }
+
+    /// The most recently detected envelope frame.
+    ///
+    /// This is the same frame that was returned by the last call to `next`.
+    pub fn current(&self) -> F {
+        self.last_env_frame.map(|s| s.to_sample())
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame.
+    pub fn next(&mut self, frame: F) -> F {
+        let mut frame = frame;
+        self.next_in_place(&mut frame);
+        frame
+    }
+
+    /// Detect the next envelope frame from the given input signal frame, overwriting the input
+    /// with the envelope.
+    ///
+    /// This is equivalent to `*frame = self.next(*frame)`, but avoids copying the returned frame,
+    /// which may be significant for frames with many channels.
+    #[inline]
+    pub fn next_in_place(&mut self, frame: &mut F) {
+        let mode_frame = self.mode.next_float_frame(*frame);
+        *frame = self.step(mode_frame);
+    }
+
+    /// Given the next input signal frame in some other frame type `H` (with the same number of
+    /// channels), convert it to the frame type `F` and then detect and return the next envelope
+    /// frame.
+    ///
+    /// This allows for a single detector to track the envelope of buffers arriving in different
+    /// sample formats, i.e. both `[i16; 2]` and `[f32; 2]` frames fed to a detector over
+    /// `[f32; 2]`. Each sample is converted via `sample::Sample::to_sample`.
+    pub fn next_from<H>(&mut self, frame: H) -> F
+        where H: Frame<NumChannels=F::NumChannels>,
+              F::Sample: sample::conv::FromSample<H::Sample>,
+    {
+        let frame: F = frame.map(|s| s.to_sample());
+        self.next(frame)
+    }
+
+    /// Detect the next envelope frame from the given `sidechain` frame, returning it alongside
+    /// the `signal` frame to which it should be applied downstream.
+    ///
+    /// The `signal` frame is passed through untouched and has no effect on the envelope. This
+    /// keeps the detection and application signals explicit, i.e. when ducking music under a
+    /// voiceover where the voiceover is the `sidechain`.
+    pub fn next_sidechain(&mut self, signal: F, sidechain: F) -> (F, F) {
+        (signal, self.next(sidechain))
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame along with the
+    /// number of frames since the envelope last attacked.
+    ///
+    /// The age is reset to `0` whenever the detected frame exceeds the current envelope on any
+    /// channel (i.e. on each new peak) and is otherwise incremented, saturating at `usize::MAX`.
+    /// This may drive a simple onset detector without a second pass over the signal.
+    ///
+    /// The age is only tracked by this method, so frames passed to `next` (or any of its other
+    /// variants) are not counted.
+    pub fn next_with_age(&mut self, frame: F) -> (F, usize) {
+        let mode_frame = self.mode.next_float_frame(frame);
+        let is_attack = self.last_env_frame.channels().zip(mode_frame.channels())
+            .any(|(l, m)| float_magnitude(l) < float_magnitude(m));
+        self.age = if is_attack { 0 } else { self.age.saturating_add(1) };
+        (self.step(mode_frame), self.age)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame along with
+    /// whether it attacked, released or held the envelope steady.
+    ///
+    /// For multichannel frames this is the **Phase** of the channel whose envelope moved the
+    /// furthest. The envelope is the same as that returned by `next`, and the phase is the same as
+    /// that subsequently returned by `last_phase`.
+    pub fn next_with_phase(&mut self, frame: F) -> (F, Phase) {
+        let env_frame = self.next(frame);
+        (env_frame, self.last_phase)
+    }
+
+    /// Given the next input signal frame, detect and return both the frame produced by the
+    /// detection **Mode** (i.e. the rectified frame for **Peak** or the RMS for **Rms**) and the
+    /// smoothed envelope frame, as `(rectified, envelope)`.
+    ///
+    /// The envelope is the same as that returned by `next`.
+    pub fn next_with_rectified(&mut self, frame: F) -> (F, F) {
+        let mode_frame = self.mode.next_float_frame(frame);
+        (mode_frame.map(|s| s.to_sample()), self.step(mode_frame))
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame along with
+    /// whether or not the input clipped, i.e. for a clip indicator on a meter.
+    ///
+    /// Clipping is flagged where any channel of the *input* frame is at or beyond the maximum
+    /// amplitude of the sample format in either direction (i.e. `1.0` for `f32` or `i16::MAX`
+    /// for `i16`), regardless of the resulting envelope.
+    pub fn next_with_clip(&mut self, frame: F) -> (F, bool) {
+        let clipped = frame.channels().any(is_full_scale);
+        (self.next(frame), clipped)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame along with
+    /// whether or not it marks an onset.
+    ///
+    /// An onset is flagged on frames where the envelope rises by more than `rise_threshold` from
+    /// the previous envelope on any channel, i.e. on each hit fed to a drum trigger. Falling and
+    /// slowly rising envelopes are never flagged.
+    pub fn next_onset(&mut self, frame: F, rise_threshold: F::Sample) -> (F, bool) {
+        let previous = self.current();
+        let env_frame = self.next(frame);
+        let threshold = rise_threshold.to_float_sample();
+        let is_onset = previous.channels().zip(env_frame.channels())
+            .any(|(p, e)| e.to_float_sample() - p.to_float_sample() > threshold);
+        (env_frame, is_onset)
+    }
+
+    /// Given the next input signal frame along with the attack and release times to use for it,
+    /// detect and return the next envelope frame.
+    ///
+    /// This is useful when the attack and/or release times are modulated at audio rate. The
+    /// detector caches the attack and release times last set (by this method or any of the
+    /// setters), and each given time is compared with `==` against the cached time after negative
+    /// and NaN times are treated as `0.0`. The gains are only recalculated via the setters when a
+    /// time differs, avoiding the redundant `powf` calls that would result from calling the
+    /// setters every frame.
+    ///
+    /// As an unchanged time is left as it is, any per-channel or slew-limited attack or release
+    /// configured via the setters remains in effect until a different time is given.
+    pub fn next_with_times(&mut self, frame: F, attack_frames: f32, release_frames: f32) -> F {
+        let attack_frames = valid_frames(attack_frames as f64);
+        let release_frames = valid_frames(release_frames as f64);
+        if attack_frames != self.attack_frames {
+            self.set_attack_frames_f64(attack_frames);
+        }
+        if release_frames != self.release_frames {
+            self.set_release_frames_f64(release_frames);
+        }
+        self.next(frame)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame without
+    /// branching on the choice between the attack and release gains.
+    ///
+    /// The comparison between the current envelope and the detected frame is converted to a
+    /// `0.0` or `1.0` mask which blends the two gains, allowing the per-channel loop to be
+    /// auto-vectorized for frames with many channels. The result is bit-identical to `next`.
+    ///
+    /// The mask only blends the one-pole gains. If the attack or release uses any other
+    /// **Smoothing** (including a slew limit), the frame is smoothed via the same per-stage path
+    /// as `next` instead, so that the **Smoothing** is still honoured.
+    pub fn next_branchless(&mut self, frame: F) -> F {
+        let mode_frame = self.mode.next_float_frame(frame);
+        if self.mode.smooths_in_float() {
+            self.branchless_in::<FloatDomain>(mode_frame)
+        } else {
+            self.branchless_in::<FrameDomain>(FrameDomain::from_float(mode_frame))
+        }
+    }
+
+    /// The same as `next_branchless`, smoothing the envelope in the **Domain** `D`.
+    fn branchless_in<D>(&mut self, mode_frame: D::Frame) -> F
+        where D: Domain<F>,
+    {
+        if !self.is_one_pole() {
+            return self.step_smoothed::<D>(mode_frame);
+        }
+        let new_env_frame = self.zip_map_gains::<D, _>(mode_frame, |l, m, attack, release| {
+            let is_attack: G = ((D::magnitude(l) < D::magnitude(m)) as u8 as f64).to_sample();
+            let gain = attack * is_attack + release * (<G as FloatSample>::identity() - is_attack);
+            D::apply_gain(l, m, gain)
+        });
+        self.store::<D>(new_env_frame)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame with all
+    /// channels linked.
+    ///
+    /// The frame produced by the detection **Mode** is first reduced to the value with the greatest
+    /// magnitude across all channels. The attack and release are then applied identically to every
+    /// channel, so that (i.e. when compressing a stereo bus) the image does not shift. The
+    /// returned frame has the same value in every channel.
+    pub fn next_linked(&mut self, frame: F) -> F {
+        let mode_frame = self.mode.next_float_frame(frame);
+        let equilibrium: <F::Sample as Sample>::Float = Sample::equilibrium();
+        let max = mode_frame.channels().fold(equilibrium, |max, s| {
+            if float_magnitude(s) > float_magnitude(max) { s } else { max }
+        });
+        self.step(Frame::from_fn(|_| max))
+    }
+
+    /// Detect the envelope of each frame in `input`, writing the resulting envelope frames to
+    /// `output`.
+    ///
+    /// This produces the same results as calling `next` for each frame in turn.
+    ///
+    /// **Panics** if the length of `input` differs from the length of `output`.
+    pub fn process_slice(&mut self, input: &[F], output: &mut [F]) {
+        assert_eq!(input.len(), output.len(),
+                   "`input` and `output` slices must be the same length");
+        for (&frame, env_frame) in input.iter().zip(output.iter_mut()) {
+            *env_frame = self.next(frame);
+        }
+    }
+
+    /// Detect the envelope of each frame in `input`, returning the per-channel maximum, minimum
+    /// and mean of the detected envelope over the block rather than the envelope itself.
+    ///
+    /// The detector is updated exactly as though `next` had been called for each frame in turn,
+    /// while the statistics are gathered in the same pass.
+    ///
+    /// If `input` is empty, every field of the **EnvelopeStats** is at equilibrium.
+    pub fn analyze(&mut self, input: &[F]) -> EnvelopeStats<F> {
+        let mut frames = input.iter();
+        let first = match frames.next() {
+            Some(&frame) => self.next(frame),
+            None => return EnvelopeStats {
+                max: F::equilibrium(),
+                min: F::equilibrium(),
+                mean: Frame::equilibrium(),
+            },
+        };
+        let (mut max, mut min, mut sum) = (first, first, first.to_float_frame());
+        for &frame in frames {
+            let env_frame = self.next(frame);
+            max = max.zip_map(env_frame, |m, e| if e > m { e } else { m });
+            min = min.zip_map(env_frame, |m, e| if e < m { e } else { m });
+            sum = sum.add_amp(env_frame.to_float_frame());
+        }
+        let len: <F::Sample as Sample>::Float = (input.len() as f64).to_sample();
+        EnvelopeStats {
+            max,
+            min,
+            mean: sum.map(|s| s / len),
+        }
+    }
+
+    /// Replace each frame in `buffer` with its detected envelope frame.
+    ///
+    /// This produces the same results as calling `next` for each frame in turn.
+    pub fn process_slice_mut(&mut self, buffer: &mut [F]) {
+        for frame in buffer.iter_mut() {
+            self.next_in_place(frame);
+        }
+    }
+
+    /// Replace each frame of the given interleaved sample buffer with its detected envelope
+    /// frame, where `N` is the number of channels.
+    ///
+    /// The buffer is chunked into frames of `N` samples, so that (i.e.) an interleaved
+    /// `&mut [f32]` from an audio callback may be processed via `process_interleaved::<2>`
+    /// without first converting it to a slice of frames. This produces the same results as
+    /// `process_slice_mut`.
+    ///
+    /// Returns an **InterleaveError** without modifying `buffer` if `N` is not the number of
+    /// channels in `F` or if the length of `buffer` is not a multiple of `N`.
+    pub fn process_interleaved<const N: usize>(&mut self, buffer: &mut [F::Sample])
+        -> Result<(), InterleaveError>
+    {
+        let n_channels = F::n_channels();
+        if N != n_channels {
+            return Err(InterleaveError::ChannelMismatch { requested: N, frame: n_channels });
+        }
+        if buffer.len() % N != 0 {
+            return Err(InterleaveError::Misaligned { len: buffer.len(), channels: N });
+        }
+        for chunk in buffer.chunks_mut(N) {
+            let frame = F::from_samples(&mut chunk.iter().cloned())
+                .expect("the chunk contains exactly one frame of samples");
+            let env_frame = self.next(frame);
+            for (sample, env_sample) in chunk.iter_mut().zip(env_frame.channels()) {
+                *sample = env_sample;
+            }
+        }
+        Ok(())
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame, applying
+    /// the attack and release in the log (decibel) domain.
+    ///
+    /// The frame produced by the detection **Mode** and the current envelope are converted to
+    /// decibels, smoothed and then converted back to linear amplitude. This produces the
+    /// constant-dB-rate ballistics of many hardware compressors, rather than the constant-ratio
+    /// ballistics of `next`. The same attack and release gains are used by both methods.
+    ///
+    /// The attack and release are always applied as one-pole smoothing of the level in decibels.
+    /// Any other **Smoothing** selected for the attack or release (including a slew limit) only
+    /// applies to `next`, and is ignored here.
+    ///
+    /// Levels at or below [**LOG_DOMAIN_FLOOR_DB**](./constant.LOG_DOMAIN_FLOOR_DB) are treated
+    /// as silence.
+    pub fn next_log_domain(&mut self, frame: F) -> F {
+        let mode_frame = self.mode.next_float_frame(frame);
+        if self.mode.smooths_in_float() {
+            self.log_domain_in::<FloatDomain>(mode_frame)
+        } else {
+            self.log_domain_in::<FrameDomain>(FrameDomain::from_float(mode_frame))
+        }
+    }
+
+    /// The same as `next_log_domain`, smoothing the envelope in the **Domain** `D`.
+    fn log_domain_in<D>(&mut self, mode_frame: D::Frame) -> F
+        where D: Domain<F>,
+    {
+        let new_env_frame = self.zip_map_gains::<D, _>(mode_frame, |l, m, attack, release| {
+            let gain = if D::magnitude(l) < D::magnitude(m) { attack } else { release };
+            let l = l.to_float_sample().to_sample::<f32>();
+            let m = m.to_float_sample().to_sample::<f32>();
+            let l_db = amp_to_db(l.abs(), LOG_DOMAIN_FLOOR_DB);
+            let m_db = amp_to_db(m.abs(), LOG_DOMAIN_FLOOR_DB);
+            let env_db = m_db + (l_db - m_db) * gain.to_sample::<f32>();
+            let env = if env_db <= LOG_DOMAIN_FLOOR_DB { 0.0 } else { db_to_amp(env_db) };
+            // Retain which side of equilibrium the envelope is on.
+            let env = if m < 0.0 || (m == 0.0 && l < 0.0) { -env } else { env };
+            env.to_sample::<<<D::Frame as Frame>::Sample as Sample>::Float>().to_sample()
+        });
+        self.store::<D>(new_env_frame)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame with an
+    /// instantaneous attack and a release that falls at a constant `db_per_sec` for a signal at
+    /// the given sample rate, as in a classic peak meter.
+    ///
+    /// Whenever the detected frame is greater in magnitude than the envelope the envelope jumps to
+    /// it, otherwise the envelope falls linearly in dB until it meets the detected frame, treating
+    /// levels at or below [**LOG_DOMAIN_FLOOR_DB**](./constant.LOG_DOMAIN_FLOOR_DB) as silence.
+    /// The attack and release times of the detector are unused. Negative and NaN rates are treated
+    /// as `0.0`, in which case the envelope never falls.
+    ///
+    /// To apply a dB-per-second release within `next` instead, see
+    /// `set_release_slew_db_per_sec`.
+    pub fn next_db_decay(&mut self, frame: F, db_per_sec: f32, sample_hz: f64) -> F {
+        let mode_frame = self.mode.next_float_frame(frame);
+        let db_per_frame = slew_db_per_frame(db_per_sec, sample_hz);
+        if self.mode.smooths_in_float() {
+            self.db_decay_in::<FloatDomain>(mode_frame, db_per_frame)
+        } else {
+            self.db_decay_in::<FrameDomain>(FrameDomain::from_float(mode_frame), db_per_frame)
+        }
+    }
+
+    /// The same as `next_db_decay`, smoothing the envelope in the **Domain** `D`.
+    fn db_decay_in<D>(&mut self, mode_frame: D::Frame, db_per_frame: f64) -> F
+        where D: Domain<F>,
+    {
+        let last_env_frame = D::from_float(self.last_env_frame);
+        let new_env_frame: D::Frame = last_env_frame.zip_map(mode_frame, |l, m| {
+            if D::magnitude(l) < D::magnitude(m) { m } else { apply_slew(l, m, db_per_frame) }
+        });
+        self.store::<D>(new_env_frame)
+    }
+
+    /// Apply the attack and release to the given frame produced by the detection **Mode**.
+    ///
+    /// Unless the **Mode** smooths in floating point, the frame and the envelope are converted to
+    /// `F` for smoothing, so that the envelope behaves exactly as though it were stored in `F`.
+    ///
+    /// Updates the `last_phase` and returns the new envelope frame.
+    fn step(&mut self, mode_frame: F::Float) -> F {
+        let previous = self.last_env_frame;
+        let env_frame = if self.mode.smooths_in_float() {
+            self.step_in::<FloatDomain>(mode_frame)
+        } else {
+            self.step_in::<FrameDomain>(FrameDomain::from_float(mode_frame))
+        };
+        let mut phase = PhaseTracker::new();
+        let channels = previous.channels().zip(mode_frame.channels());
+        for ((l, m), env) in channels.zip(self.last_env_frame.channels()) {
+            phase.update(l, env, float_magnitude(l) < float_magnitude(m));
+        }
+        self.last_phase = phase.phase;
+        env_frame
+    }
+
+    /// The same as `step`, smoothing the envelope in the **Domain** `D`.
+    fn step_in<D>(&mut self, mode_frame: D::Frame) -> F
+        where D: Domain<F>,
+    {
+        if !self.is_one_pole() {
+            return self.step_smoothed::<D>(mode_frame);
+        }
+        let new_env_frame = self.zip_map_gains::<D, _>(mode_frame, |l, m, attack, release| {
+            let is_attack = D::magnitude(l) < D::magnitude(m);
+            D::apply_gain(l, m, if is_attack { attack } else { release })
+        });
+        self.store::<D>(new_env_frame)
+    }
+
+    /// Whether both the attack and the release (unless bypassed, see **Mode::applies_release**)
+    /// use **Smoothing::OnePole**, in which case the gains are applied directly rather than via
+    /// a **Stage**.
+    fn is_one_pole(&self) -> bool {
+        self.attack_smoothing == Smoothing::OnePole
+            && (self.release_smoothing == Smoothing::OnePole || self.mode.applies_release())
+    }
+
+    /// Store the given envelope frame, smoothed in the **Domain** `D`, returning it as `F`.
+    ///
+    /// Every method that detects a new envelope frame stores it here, so this is also where the
+    /// `session_peak` is updated.
+    fn store<D>(&mut self, env_frame: D::Frame) -> F
+        where D: Domain<F>,
+    {
+        self.last_env_frame = D::to_float(env_frame);
+        let env_frame = self.last_env_frame.map(|s| s.to_sample());
+        // Retain the envelope of greatest magnitude in each channel.
+        self.session_peak = self.session_peak.zip_map(env_frame, |p, e| {
+            if magnitude(e) > magnitude(p) { e } else { p }
+        });
+        env_frame
+    }
+
+    /// The same as `step_in`, but applying the **Smoothing** of the attack and/or release where
+    /// it is not **Smoothing::OnePole**.
+    fn step_smoothed<D>(&mut self, mode_frame: D::Frame) -> F
+        where D: Domain<F>,
+    {
+        let attack_stage = self.attack_smoothing.stage::<F, D, G>(self.ballistics);
+        let release_smoothing = if self.mode.applies_release() {
+            Smoothing::OnePole
+        } else {
+            self.release_smoothing
+        };
+        let release_stage = release_smoothing.stage::<F, D, G>(self.ballistics);
+        let new_env_frame = self.zip_map_gains::<D, _>(mode_frame, |l, m, attack, release| {
+            if D::magnitude(l) < D::magnitude(m) {
+                attack_stage.apply(l, m, attack)
+            } else {
+                release_stage.apply(l, m, release)
+            }
+        });
+        self.store::<D>(new_env_frame)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame in decibels
+    /// relative to full scale (dBFS).
+    ///
+    /// Each channel's envelope magnitude is converted via `20 * log10(x)`. Silence (and anything
+    /// quieter than `floor_db`, e.g. `-120.0`) yields `floor_db` rather than negative infinity.
+    pub fn next_db(&mut self, frame: F, floor_db: f32) -> F::Float {
+        self.next(frame).to_float_frame().map(|s: <F::Sample as Sample>::Float| {
+            let amp = s.to_sample::<f32>().abs();
+            amp_to_db(amp, floor_db).to_sample()
+        })
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame normalised
+    /// to the range `[0.0, 1.0]` relative to full scale.
+    ///
+    /// Each channel's envelope is converted to the equivalent floating point sample format (where
+    /// full scale is `1.0` regardless of the signal's sample format) and its magnitude is clamped
+    /// to `1.0`. This allows integer and floating point signals to drive i.e. the same LED meter.
+    pub fn next_normalized(&mut self, frame: F) -> F::Float {
+        self.next(frame).to_float_frame().map(|s: <F::Sample as Sample>::Float| {
+            let amp = s.to_sample::<f32>().abs();
+            if amp > 1.0 { 1.0.to_sample() } else { amp.to_sample() }
+        })
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame with each
+    /// sample clamped so that it does not exceed `ceiling`.
+    ///
+    /// Only the returned frame is clamped, so the envelope used for the next frame continues to
+    /// track the true envelope. The clamped frame may be passed to `reset_to` if the internal
+    /// envelope should be clamped too.
+    pub fn next_clamped(&mut self, frame: F, ceiling: F::Sample) -> F {
+        self.next(frame).map(|s| if s > ceiling { ceiling } else { s })
+    }
+
+    /// Given the next input signal frame, return the linear gain that a compressor with the given
+    /// threshold and ratio would apply for each channel.
+    ///
+    /// The envelope returned by `next` is converted to decibels. Where it exceeds `threshold_db`
+    /// the gain reduction is `(env_db - threshold_db) * (1 / ratio - 1)` decibels, otherwise no
+    /// reduction is applied. The gain reduction is returned as a linear multiplier in the range
+    /// `(0.0, 1.0]`.
+    ///
+    /// A `ratio` of `1.0` or less (or NaN) applies no gain reduction.
+    pub fn gain_reduction(&mut self, frame: F, threshold_db: f32, ratio: f32) -> F::Float {
+        self.next_db(frame, LOG_DOMAIN_FLOOR_DB).map(|env_db: <F::Sample as Sample>::Float| {
+            let env_db = env_db.to_sample::<f32>();
+            let reduction_db = calc_gain_reduction_db(env_db, threshold_db, ratio);
+            db_to_amp(reduction_db).to_sample()
+        })
+    }
+
+    /// Given the next input signal frame, return the linear gain that a soft knee compressor with
+    /// the given threshold, ratio and knee width would apply for each channel.
+    ///
+    /// This is the same as `gain_reduction`, except that within the region of `knee_db` centred
+    /// on `threshold_db` the gain reduction is interpolated quadratically from no reduction to
+    /// the full ratio. This provides a smooth transition into compression. A `knee_db` of `0.0`
+    /// produces the same results as `gain_reduction`.
+    pub fn gain_reduction_soft(&mut self,
+                               frame: F,
+                               threshold_db: f32,
+                               ratio: f32,
+                               knee_db: f32) -> F::Float
+    {
+        self.next_db(frame, LOG_DOMAIN_FLOOR_DB).map(|env_db: <F::Sample as Sample>::Float| {
+            let env_db = env_db.to_sample::<f32>();
+            let reduction_db = calc_soft_gain_reduction_db(env_db, threshold_db, ratio, knee_db);
+            db_to_amp(reduction_db).to_sample()
+        })
+    }
+
+    /// Given the next sidechain frame, return the linear gain with which to duck some other signal
+    /// for each channel, i.e. to lower music beneath a voice.
+    ///
+    /// The envelope returned by `next` is converted to decibels. Where it exceeds `threshold_db`
+    /// the gain is reduced by one decibel for each decibel above the threshold, up to a maximum
+    /// reduction of `depth_db` (a positive number of decibels). Below the threshold the gain is
+    /// `1.0`. The gain is returned as a linear multiplier in the range `(0.0, 1.0]`, falling as
+    /// the sidechain envelope rises.
+    ///
+    /// A `depth_db` of `0.0` or less (or NaN) applies no gain reduction.
+    pub fn duck_gain(&mut self, frame: F, threshold_db: f32, depth_db: f32) -> F::Float {
+        self.next_db(frame, LOG_DOMAIN_FLOOR_DB).map(|env_db: <F::Sample as Sample>::Float| {
+            let env_db = env_db.to_sample::<f32>();
+            db_to_amp(calc_duck_db(env_db, threshold_db, depth_db)).to_sample()
+        })
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope average across each
+    /// channel for the frame.
+    ///
+    /// The returned value will take the form of the signal's equivalent floating point sample
+    /// format.
+    pub fn next_avg(&mut self, frame: F) -> <F::Sample as Sample>::Float {
+        let next_frame = self.next(frame);
+        let equilibrium: F::Sample = Sample::equilibrium();
+        let sum = next_frame.channels().fold(equilibrium, |sum, s| sum.add_amp(s.to_sample()));
+        sum.to_float_sample() / (F::n_channels() as f32).to_sample()
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use sample::types::{I24, I48};
+    use super::*;
+
+    #[test]
+    fn full_scale_is_within_range_for_every_format() {
+        assert_eq!(<f32 as FullScale>::MAX, 1.0);
+        assert_eq!(<f64 as FullScale>::MIN, -1.0);
+        assert_eq!(<i16 as FullScale>::MAX, i16::MAX);
+        assert_eq!(<i16 as FullScale>::MIN, i16::MIN);
+        assert_eq!(<I24 as FullScale>::MAX, I24::new(8_388_607).unwrap());
+        assert_eq!(<I24 as FullScale>::MIN, I24::new(-8_388_608).unwrap());
+        assert_eq!(<I48 as FullScale>::MAX, I48::new(140_737_488_355_327).unwrap());
+        assert_eq!(<I48 as FullScale>::MIN, I48::new(-140_737_488_355_328).unwrap());
+        assert_eq!(<i64 as FullScale>::MAX, i64::MAX);
+        assert_eq!(<u16 as FullScale>::MIN, i16::MIN);
+    }
+
+    #[test]
+    fn full_scale_step_is_a_single_step_of_every_integer_format() {
+        assert_eq!(<f32 as FullScale>::STEP, 0.0);
+        assert_eq!(<f64 as FullScale>::STEP, 0.0);
+        assert_eq!((1.0 - <i16 as FullScale>::STEP).to_sample::<i16>(), i16::MAX);
+        assert_eq!((1.0 - <I24 as FullScale>::STEP).to_sample::<f32>().to_sample::<I24>(),
+                   <I24 as FullScale>::MAX);
+        assert_eq!((1.0 - <I48 as FullScale>::STEP).to_sample::<I48>(), <I48 as FullScale>::MAX);
+        assert_eq!(<u8 as FullScale>::STEP, <i8 as FullScale>::STEP);
+    }
+
+    #[test]
+    fn saturating_neg_clamps_the_minimum() {
+        assert_eq!(saturating_neg::<i16>(i16::MIN), i16::MAX);
+        assert_eq!(saturating_neg::<i16>(-5), 5);
+        assert_eq!(saturating_neg::<I24>(<I24 as FullScale>::MIN), <I24 as FullScale>::MAX);
+        assert_eq!(saturating_neg::<I48>(<I48 as FullScale>::MIN), <I48 as FullScale>::MAX);
+        assert_eq!(saturating_neg::<f32>(-0.5), 0.5);
+    }
+
+    #[test]
+    fn is_full_scale_detects_integer_extremes() {
+        assert!(is_full_scale(<I24 as FullScale>::MAX));
+        assert!(is_full_scale(<I24 as FullScale>::MIN));
+        assert!(!is_full_scale(I24::new(8_388_606).unwrap()));
+        assert!(is_full_scale(<I48 as FullScale>::MIN));
+        assert!(!is_full_scale(I48::new(0).unwrap()));
+        assert!(is_full_scale(u8::MAX));
+        assert!(is_full_scale(-1.0f32));
+        assert!(!is_full_scale(0.999f32));
+    }
+
+    #[test]
+    fn apply_gain_steps_i24_onto_full_scale() {
+        let max = <I24 as FullScale>::MAX;
+        let mut env = I24::new(8_388_000).unwrap();
+        for _ in 0..100_000 {
+            env = apply_gain(env, max, 0.999);
+        }
+        assert_eq!(env, max);
+    }
+
+    #[test]
+    fn apply_gain_is_a_multiply_add_for_float_formats() {
+        for &(l, m, gain) in &[(0.3f64, 0.7, 0.25), (0.9, -0.2, 0.999), (1.0, 0.0, 0.5)] {
+            assert_eq!(apply_gain(l, m, gain), m + (l - m) * gain);
+            let (l, m, gain) = (l as f32, m as f32, gain as f32);
+            assert_eq!(apply_gain(l, m, gain), m + (l - m) * gain);
+        }
+    }
+
+    #[test]
+    fn f64_gains_track_a_long_release_more_precisely_than_f32_gains() {
+        let release_frames = 441_000.0;
+        let mut precise = EnvelopeDetector::<[f64; 1], _>::peak(0.0, release_frames)
+            .with_gain_type::<f64>();
+        // The gains are `f32` by default.
+        let mut coarse: EnvelopeDetector<[f64; 1], Peak, f32> =
+            EnvelopeDetector::peak(0.0, release_frames);
+        precise.reset_to([1.0]);
+        coarse.reset_to([1.0]);
+        let n_frames = 100_000;
+        for _ in 0..n_frames {
+            precise.next([0.0]);
+            coarse.next([0.0]);
+        }
+        let expected = (-n_frames as f64 / release_frames as f64).exp();
+        let precise_error = (precise.current()[0] - expected).abs();
+        let coarse_error = (coarse.current()[0] - expected).abs();
+        assert!(precise_error < 1e-9, "{}", precise_error);
+        assert!(coarse_error > 1_000.0 * precise_error, "{} {}", coarse_error, precise_error);
+        assert_eq!(coarse.attack_frames(), precise.attack_frames());
+        assert_eq!(coarse.release_gain(), precise.release_gain());
+    }
+
+    #[test]
+    fn peak_of_i24_minimum_saturates_at_the_maximum() {
+        let mut detector = EnvelopeDetector::<[I24; 1], _>::peak(0.0, 0.0);
+        assert_eq!(detector.next([<I24 as FullScale>::MIN]), [<I24 as FullScale>::MAX]);
+    }
+
+    #[test]
+    fn set_release_frames_leaves_the_attack_untouched() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 10.0);
+        detector.set_release_frames(1000.0);
+        let mut reference = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 1000.0);
+        let mut level = 0.0;
+        for _ in 0..200 {
+            level = detector.next([1.0])[0];
+            assert_eq!([level], reference.next([1.0]));
+        }
+        // After a step down the envelope should take the new release time to fall by 1/e.
+        let mut n_frames = 0;
+        while detector.next([0.0])[0] > level / ::std::f32::consts::E {
+            n_frames += 1;
+        }
+        assert!((998..=1001).contains(&n_frames), "{}", n_frames);
+    }
+
+    #[test]
+    fn frame_getters_round_trip_the_constructor() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 250.0);
+        assert!((detector.attack_frames() - 10.0).abs() < 1e-3, "{}", detector.attack_frames());
+        assert!((detector.release_frames() - 250.0).abs() < 0.1, "{}", detector.release_frames());
+        detector.set_release_frames(0.0);
+        assert_eq!(detector.release_frames(), 0.0);
+    }
+
+    #[test]
+    fn frames_from_gain_inverts_gain_from_frames() {
+        for &n in &[0.5f32, 1.0, 10.0, 100.0, 1000.0] {
+            let round_trip = frames_from_gain(gain_from_frames(n));
+            assert!((round_trip - n).abs() / n < 1e-2, "{} {}", n, round_trip);
+        }
+    }
+
+    #[test]
+    fn attack_and_release_gains_match_gain_from_frames() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 200.0);
+        assert_eq!(detector.attack_gain(), gain_from_frames(10.0));
+        assert_eq!(detector.release_gain(), gain_from_frames(200.0));
+    }
+
+    #[test]
+    fn reset_returns_to_the_initial_state() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::rms(4, 2.0, 8.0);
+        let mut fresh = detector.clone();
+        for i in 0..20 {
+            detector.next([i as f32 * 0.05]);
+        }
+        detector.reset();
+        for i in 0..20 {
+            let x = [i as f32 * 0.01];
+            assert_eq!(detector.next(x), fresh.next(x));
+        }
+    }
+
+    #[test]
+    fn reset_to_seeds_the_envelope_and_current_reads_it() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 10.0);
+        assert_eq!(detector.current(), [0.0]);
+        detector.reset_to([0.5]);
+        assert_eq!(detector.current(), [0.5]);
+        let env = detector.next([0.0]);
+        assert!(env[0] < 0.5 && env[0] > 0.4, "{:?}", env);
+        assert_eq!(detector.current(), env);
+    }
+
+    #[test]
+    fn default_peak_detector_uses_the_documented_times() {
+        let detector = PeakEnvelopeDetector::<[f32; 1]>::default();
+        assert_eq!(detector, EnvelopeDetector::peak(1.0, 100.0));
+    }
+
+    #[test]
+    fn invalid_frame_counts_act_as_instant() {
+        for &frames in &[-5.0f32, 0.0, f32::NAN] {
+            let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(frames, frames);
+            for _ in 0..10 {
+                let env = detector.next([0.5]);
+                assert!(env[0] >= 0.0 && env[0] <= 0.5, "{:?}", env);
+            }
+        }
+    }
+
+    #[test]
+    fn durations_convert_to_frames() {
+        use std::time::Duration;
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak_from_durations(
+            Duration::from_millis(1),
+            Duration::from_millis(0),
+            44_100.0,
+        );
+        assert!((detector.attack_frames() - 44.1).abs() < 1e-3);
+        assert_eq!(detector.release_frames(), 0.0);
+        let rms = EnvelopeDetector::<[f32; 1], _>::rms_from_durations(
+            Duration::from_millis(10),
+            Duration::from_millis(1),
+            Duration::from_millis(1),
+            44_100.0,
+        );
+        assert_eq!(rms.mode().window_frames(), 441);
+    }
+
+    #[test]
+    fn time_constant_reaches_63_percent() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(1.0, 1.0);
+        detector.set_attack_time_constant(0.01, 1000.0);
+        let mut env = [0.0];
+        for _ in 0..10 {
+            env = detector.next([1.0]);
+        }
+        assert!((env[0] - (1.0 - 1.0 / ::std::f32::consts::E)).abs() < 0.03, "{:?}", env);
+    }
+
+    #[test]
+    fn set_window_ms_matches_set_window_frames() {
+        let mut ms = EnvelopeDetector::<[f32; 1], _>::rms(8, 2.0, 20.0);
+        let mut frames = EnvelopeDetector::<[f32; 1], _>::rms(8, 2.0, 20.0);
+        for i in 0..100 {
+            let x = [(i as f32 * 0.1).sin()];
+            assert_eq!(ms.next(x), frames.next(x));
+        }
+        ms.set_window_ms(10.0, 44_100.0);
+        frames.set_window_frames(441);
+        for i in 0..1000 {
+            let x = [(i as f32 * 0.07).cos()];
+            assert_eq!(ms.next(x), frames.next(x));
+        }
+    }
+
+    #[test]
+    fn next_db_clamps_to_the_floor() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+        assert!(detector.next_db([1.0], -120.0)[0].abs() < 1e-4);
+        assert_eq!(detector.next_db([0.0], -120.0)[0], -120.0);
+        let mut integer = EnvelopeDetector::<[i16; 1], _>::peak(0.0, 0.0);
+        assert!(integer.next_db([i16::MAX], -120.0)[0].abs() < 1e-3);
+    }
+
+    #[test]
+    fn next_linked_drives_every_channel_from_the_loudest() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(2.0, 5.0);
+        for i in 0..10 {
+            let env = detector.next_linked([if i < 3 { 0.9 } else { 0.0 }, 0.0]);
+            assert_eq!(env[0], env[1]);
+            if i == 0 {
+                assert!(env[1] > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn process_slice_matches_next() {
+        let input: Vec<[f32; 2]> = (0..100)
+            .map(|i| [(i as f32 * 0.2).sin(), (i % 9) as f32 * 0.1])
+            .collect();
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::rms(8, 2.0, 10.0);
+        let mut reference = detector.clone();
+        let mut output = vec![[0.0; 2]; input.len()];
+        detector.process_slice(&input, &mut output);
+        for (&frame, &env) in input.iter().zip(&output) {
+            assert_eq!(env, reference.next(frame));
+        }
+    }
+
+    #[test]
+    fn next_log_domain_attacks_along_a_decibel_curve() {
+        let mut linear = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 10.0);
+        let mut log = linear;
+        linear.reset_to([0.01]);
+        log.reset_to([0.01]);
+        let x = linear.next([1.0])[0];
+        let y = log.next_log_domain([1.0])[0];
+        assert!((x - y).abs() > 0.01, "{} {}", x, y);
+        assert!(y > 0.01 && y < 1.0);
+        let mut silent = EnvelopeDetector::<[f32; 1], _>::peak(1.0, 1.0);
+        assert_eq!(silent.next_log_domain([0.0]), [0.0]);
+    }
+
+    #[test]
+    fn gain_reduction_applies_the_ratio_above_threshold() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+        let gain = detector.gain_reduction([1.0], -6.0, 2.0)[0];
+        assert!((20.0 * gain.log10() + 3.0).abs() < 1e-3, "{}", gain);
+        assert_eq!(detector.gain_reduction([1.0], -6.0, 0.5)[0], 1.0);
+        assert_eq!(detector.gain_reduction([0.1], -6.0, 4.0)[0], 1.0);
+    }
+
+    #[test]
+    fn next_with_times_matches_setting_the_times_each_frame() {
+        let mut per_frame = EnvelopeDetector::<[f32; 1], _>::peak(1.0, 10.0);
+        let mut setter = per_frame;
+        for i in 0..200 {
+            let x = [(i as f32 * 0.1).sin()];
+            let attack = 1.0 + (i % 7) as f32;
+            let release = 10.0 + (i / 3) as f32;
+            setter.set_attack_frames(attack);
+            setter.set_release_frames(release);
+            assert_eq!(per_frame.next_with_times(x, attack, release), setter.next(x));
+        }
+
+        // A time set at `f64` precision is recalculated once given rounded to `f32`.
+        per_frame.set_release_frames_f64(4.41);
+        setter.set_release_frames(4.41);
+        assert_eq!(per_frame.next_with_times([0.0], 1.0, 4.41), setter.next([0.0]));
+        assert_eq!(per_frame.release_frames(), 4.41);
+        setter.set_attack_frames(0.0);
+        assert_eq!(per_frame.next_with_times([0.0], f32::NAN, 4.41), setter.next([0.0]));
+        assert_eq!(per_frame.attack_frames(), 0.0);
+    }
+
+    #[test]
+    fn next_sidechain_passes_the_main_signal_through() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(1.0, 10.0);
+        let mut reference = detector;
+        for i in 0..50 {
+            let key = [(i as f32 * 0.3).sin()];
+            let (main, env) = detector.next_sidechain([i as f32], key);
+            assert_eq!(main, [i as f32]);
+            assert_eq!(env, reference.next(key));
+        }
+    }
+
+    #[test]
+    fn set_attack_release_frames_sets_both() {
+        let mut both = EnvelopeDetector::<[f32; 1], _>::peak(1.0, 10.0);
+        let mut separate = both;
+        both.set_attack_release_frames(5.0, -3.0);
+        separate.set_attack_frames(5.0);
+        separate.set_release_frames(-3.0);
+        assert_eq!(both.attack_frames(), separate.attack_frames());
+        assert_eq!(both.release_frames(), separate.release_frames());
+    }
+
+    #[test]
+    fn vu_and_ppm_presets_follow_their_ballistics() {
+        let sample_hz = 1000.0;
+        let mut vu = EnvelopeDetector::<[f32; 1], _>::vu(sample_hz);
+        let mut n_frames = 0;
+        loop {
+            n_frames += 1;
+            if vu.next([1.0])[0] >= 0.99 {
+                break;
+            }
+        }
+        assert!((280..=300).contains(&n_frames), "{}", n_frames);
+        let mut ppm = EnvelopeDetector::<[f32; 1], _>::ppm(sample_hz);
+        let mut env = 0.0;
+        for _ in 0..10 {
+            env = ppm.next([1.0])[0];
+        }
+        assert!((env - 0.63).abs() < 0.05, "{}", env);
+    }
+
+    #[test]
+    fn next_normalized_scales_to_unity() {
+        let mut integer = EnvelopeDetector::<[i16; 1], _>::peak(0.0, 0.0);
+        let mut float = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+        let x = integer.next_normalized([i16::MAX])[0];
+        let y = float.next_normalized([1.0])[0];
+        assert!((x - 1.0).abs() < 0.001 && (y - 1.0).abs() < 1e-6, "{} {}", x, y);
+    }
+
+    #[test]
+    fn next_branchless_is_bit_identical_to_next() {
+        let mut detector = EnvelopeDetector::<[f32; 16], _>::peak(3.0, 50.0);
+        let mut branchless = detector;
+        let mut seed = 12_345u32;
+        for _ in 0..2000 {
+            let mut x = [0.0f32; 16];
+            for sample in x.iter_mut() {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                *sample = (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0;
+            }
+            let expected = detector.next(x);
+            let env = branchless.next_branchless(x);
+            for c in 0..16 {
+                assert_eq!(env[c].to_bits(), expected[c].to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn long_release_decays_monotonically() {
+        let sample_hz = 44_100.0;
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 10.0 * sample_hz);
+        detector.next([1.0]);
+        let mut last = 1.0f32;
+        for i in 1..(sample_hz as usize * 10) {
+            let env = detector.next([0.0])[0];
+            if i % 4410 == 0 {
+                assert!(env < last, "{} {}", env, last);
+                last = env;
+            }
+        }
+        assert!((last - (-1f32).exp()).abs() < 0.005, "{}", last);
+    }
+
+    #[test]
+    fn process_interleaved_matches_process_slice_mut() {
+        let mut interleaved = EnvelopeDetector::<[f32; 2], _>::peak(2.0, 20.0);
+        let mut framed = interleaved;
+        let mut buffer: Vec<f32> = (0..200).map(|i| (i as f32 * 0.37).sin()).collect();
+        let mut frames: Vec<[f32; 2]> = buffer.chunks(2).map(|c| [c[0], c[1]]).collect();
+        interleaved.process_interleaved::<2>(&mut buffer).unwrap();
+        framed.process_slice_mut(&mut frames);
+        let flat: Vec<f32> = frames.iter().flat_map(|f| f.iter().cloned()).collect();
+        assert_eq!(buffer, flat);
+
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(2.0, 20.0);
+        assert_eq!(
+            detector.process_interleaved::<2>(&mut [0.0; 3]),
+            Err(InterleaveError::Misaligned { len: 3, channels: 2 })
+        );
+        assert_eq!(
+            detector.process_interleaved::<1>(&mut [0.0; 4]),
+            Err(InterleaveError::ChannelMismatch { requested: 1, frame: 2 })
+        );
+    }
+
+    #[test]
+    fn detectors_compare_by_state() {
+        let mut a = EnvelopeDetector::<[f32; 2], _>::rms(4, 1.0, 5.0);
+        let mut b = EnvelopeDetector::<[f32; 2], _>::rms(4, 1.0, 5.0);
+        assert_eq!(a, b);
+        a.next([0.5, 0.1]);
+        assert!(a != b);
+        b.next([0.5, 0.1]);
+        assert_eq!(a, b);
+        b.next([0.2, 0.2]);
+        assert!(a != b);
+    }
+
+    #[test]
+    fn analog_ballistics_differ_from_digital_but_settle_the_same() {
+        let mut digital = EnvelopeDetector::<[f32; 1], _>::with_ballistics(
+            Peak::full_wave(), 2.0, 2.0, Ballistics::Digital);
+        let mut analog = EnvelopeDetector::<[f32; 1], _>::with_ballistics(
+            Peak::full_wave(), 2.0, 2.0, Ballistics::Analog);
+        let (x, y) = (digital.next([1.0])[0], analog.next([1.0])[0]);
+        assert!((x - y).abs() > 0.05, "{} {}", x, y);
+        for _ in 0..200 {
+            digital.next([1.0]);
+            analog.next([1.0]);
+        }
+        assert!((digital.current()[0] - 1.0).abs() < 1e-5);
+        assert!((analog.current()[0] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn next_clamped_limits_the_output_but_not_the_state() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(2.0, 20.0);
+        let mut reference = detector;
+        for i in 0..300 {
+            let x = [(i as f32 * 0.1).sin() * 1.5];
+            let clamped = detector.next_clamped(x, 0.8);
+            assert!(clamped[0] <= 0.8);
+            assert_eq!(detector.current(), reference.next(x));
+        }
+    }
+
+    #[test]
+    fn instant_attack_follows_rises_exactly() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak_instant_attack(50.0);
+        assert_eq!(detector.attack_gain(), 0.0);
+        assert_eq!(detector.next([0.3])[0], 0.3);
+        assert_eq!(detector.next([0.9])[0], 0.9);
+        let env = detector.next([0.0])[0];
+        assert!(env < 0.9 && env > 0.85, "{}", env);
+    }
+
+    #[test]
+    fn soft_knee_blends_into_the_hard_knee() {
+        let db = |x: f32| 20.0 * x.log10();
+        let amp = |db: f32| 10f32.powf(db / 20.0);
+        let soft = |level: f32, knee: f32| {
+            let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+            db(detector.gain_reduction_soft([amp(level)], -20.0, 4.0, knee)[0])
+        };
+        let hard = |level: f32| {
+            let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+            db(detector.gain_reduction([amp(level)], -20.0, 4.0)[0])
+        };
+        assert!(soft(-25.0, 10.0).abs() < 1e-3);
+        assert!((soft(-15.0, 10.0) - hard(-15.0)).abs() < 1e-3);
+        assert!((soft(-20.0, 10.0) + 0.75 * 10.0 / 8.0).abs() < 1e-3, "{}", soft(-20.0, 10.0));
+        assert!((soft(-10.0, 0.0) - hard(-10.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn oversampling_scales_the_frame_counts() {
+        let mut oversampled = EnvelopeDetector::<[f32; 1], _>::peak(100.0, 10.0);
+        oversampled.set_oversample_factor(4.0);
+        let mut scaled = EnvelopeDetector::<[f32; 1], _>::peak(400.0, 40.0);
+        assert!((oversampled.attack_frames() - 100.0).abs() < 0.01);
+        for _ in 0..1000 {
+            let (x, y) = (oversampled.next([1.0])[0], scaled.next([1.0])[0]);
+            assert!((x - y).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn integer_envelopes_saturate_near_full_scale() {
+        let mut detector = EnvelopeDetector::<[i16; 1], _>::peak(10.0, 10.0);
+        for _ in 0..2000 {
+            assert!(detector.next([i16::MAX])[0] >= 0);
+        }
+        assert!(detector.current()[0] >= i16::MAX - 16);
+        detector.reset_to([i16::MAX]);
+        for _ in 0..100 {
+            assert_eq!(detector.next([i16::MAX])[0], i16::MAX);
+        }
+        for _ in 0..2000 {
+            assert!(detector.next([i16::MIN])[0] >= 0);
+        }
+        assert_eq!(detector.current()[0], i16::MAX);
+    }
+
+    #[test]
+    fn integer_rms_rounds_instead_of_truncating() {
+        let mut detector = EnvelopeDetector::<[i16; 1], _>::rms(64, 200.0, 200.0);
+        let mut reference = EnvelopeDetector::<[f32; 1], _>::rms(64, 200.0, 200.0);
+        let mut max_error = 0.0f32;
+        for i in 0..20_000 {
+            let x = ((i as f32 * 0.05).sin() * 3000.0) as i16;
+            let expected = reference.next([x as f32 / 32_768.0])[0] * 32_768.0;
+            let env = detector.next([x])[0] as f32;
+            if i > 5000 {
+                max_error = max_error.max((env - expected).abs());
+            }
+        }
+        assert!(max_error <= 1.0, "{}", max_error);
+    }
+
+    #[test]
+    fn integer_envelopes_reach_their_target() {
+        let mut detector = EnvelopeDetector::<[i16; 1], _>::peak(100.0, 400.0);
+        for _ in 0..20_000 {
+            detector.next([20_000]);
+        }
+        assert_eq!(detector.current()[0], 20_000);
+        for _ in 0..20_000 {
+            detector.next([-300]);
+        }
+        for _ in 0..100 {
+            assert_eq!(detector.next([300])[0], 300);
+        }
+        let mut unsigned = EnvelopeDetector::<[u8; 1], _>::peak(50.0, 50.0);
+        for _ in 0..5000 {
+            unsigned.next([200]);
+        }
+        assert_eq!(unsigned.current()[0], 200);
+        let mut signed = EnvelopeDetector::<[i16; 1], _>::new(Peak::signed(), 50.0, 50.0);
+        for _ in 0..5000 {
+            signed.next([-1234]);
+        }
+        assert_eq!(signed.current()[0], -1234);
+    }
+
+    #[test]
+    fn next_in_place_matches_next() {
+        let mut detector = EnvelopeDetector::<[f32; 32], _>::rms(8, 3.0, 20.0);
+        let mut in_place = detector.clone();
+        for i in 0..200 {
+            let mut x = [0.0; 32];
+            for (c, sample) in x.iter_mut().enumerate() {
+                *sample = ((i * (c + 1)) as f32 * 0.1).sin();
+            }
+            let env = detector.next(x);
+            in_place.next_in_place(&mut x);
+            assert_eq!(&env[..], &x[..]);
+        }
+    }
+
+    #[test]
+    fn next_with_age_counts_frames_since_the_last_attack() {
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(2.0, 500.0);
+        for i in 0..1000 {
+            let x = if i % 100 == 0 { 1.0 } else { 0.0 };
+            let (env, age) = detector.next_with_age([x]);
+            assert_eq!(age, i % 100, "{}", i);
+            assert_eq!(env, detector.current());
+        }
+        detector.reset();
+        assert_eq!(detector.next_with_age([0.0]).1, 1);
+    }
+
+    #[test]
+    fn try_rms_rejects_invalid_parameters() {
+        type Detector = RmsEnvelopeDetector<[f32; 1]>;
+        assert_eq!(Detector::try_rms(0, 1.0, 1.0).unwrap_err(), EnvelopeError::ZeroWindow);
+        assert_eq!(
+            Detector::try_rms(4, f32::NAN, 1.0).unwrap_err(),
+            EnvelopeError::NonFiniteAttack
+        );
+        assert_eq!(
+            Detector::try_rms(4, 1.0, f32::INFINITY).unwrap_err(),
+            EnvelopeError::NonFiniteRelease
+        );
+        assert_eq!(Detector::try_rms(4, 2.0, 3.0).unwrap(), Detector::rms(4, 2.0, 3.0));
+    }
+
+    #[test]
+    fn set_mode_keeps_the_envelope_and_gains() {
+        let mut detector: AnyEnvelopeDetector<[f32; 1]> =
+            EnvelopeDetector::new(AnyMode::from(Rms::new(480)), 48.0, 480.0);
+        let x = |i: usize| [(i as f32 * 2.0 * ::std::f32::consts::PI * 100.0 / 48_000.0).sin()];
+        for i in 0..9600 {
+            detector.next(x(i));
+        }
+        let before = detector.current();
+        let gains = (detector.attack_gain(), detector.release_gain());
+        detector.set_mode(AnyMode::from(Peak::full_wave()));
+        assert_eq!(detector.current(), before);
+        assert_eq!((detector.attack_gain(), detector.release_gain()), gains);
+        let env = detector.next(x(9600));
+        assert!((env[0] - before[0]).abs() < 0.05, "{:?} {:?}", before, env);
+    }
+
+    #[test]
+    fn analyze_summarises_the_envelope() {
+        let input: Vec<[f32; 2]> = (0..500)
+            .map(|i| [(i as f32 * 0.07).sin(), if i % 50 == 0 { -1.0 } else { 0.1 }])
+            .collect();
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(3.0, 40.0);
+        let mut reference = detector;
+        let stats = detector.analyze(&input);
+        let envs: Vec<[f32; 2]> = input.iter().map(|&x| reference.next(x)).collect();
+        assert_eq!(detector, reference);
+        for c in 0..2 {
+            let max = envs.iter().map(|e| e[c]).fold(f32::MIN, f32::max);
+            let min = envs.iter().map(|e| e[c]).fold(f32::MAX, f32::min);
+            let mean = envs.iter().map(|e| e[c]).sum::<f32>() / 500.0;
+            assert_eq!(stats.max[c], max);
+            assert_eq!(stats.min[c], min);
+            assert!((stats.mean[c] - mean).abs() < 1e-5);
+        }
+        let empty = detector.analyze(&[]);
+        assert_eq!(empty.max, [0.0; 2]);
+        assert_eq!(empty.mean, [0.0; 2]);
+    }
+
+    #[test]
+    fn slew_limits_the_rate_in_decibels() {
+        let sample_hz = 48_000.0;
+        let db = |x: f32| 20.0 * x.abs().log10();
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 100.0);
+        detector.set_release_slew_db_per_sec(20.0, sample_hz);
+        detector.reset_to([1.0]);
+        let mut env = 0.0;
+        for _ in 0..48_000 {
+            env = detector.next([0.0])[0];
+        }
+        assert!((db(env) + 20.0).abs() < 0.01, "{}", db(env));
+
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak(100.0, 0.0);
+        detector.set_attack_slew_db_per_sec(30.0, sample_hz);
+        detector.reset_to([0.001]);
+        for _ in 0..48_000 {
+            env = detector.next([1.0])[0];
+        }
+        assert!((db(env) + 30.0).abs() < 0.01, "{}", db(env));
+        detector.set_attack_frames(0.0);
+        assert_eq!(detector.attack_slew_db_per_frame(), None);
+        assert_eq!(detector.next([1.0])[0], 1.0);
+    }
+
+    #[test]
+    fn next_onset_flags_threshold_crossings() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(0.0, 200.0);
+        let mut n_onsets = 0;
+        for i in 0..2000 {
+            let x = if i % 250 == 7 { [0.0, 0.8] } else { [0.0, 0.0] };
+            let (env, onset) = detector.next_onset(x, 0.1);
+            assert_eq!(onset, i % 250 == 7, "{}", i);
+            assert_eq!(env, detector.current());
+            if onset {
+                n_onsets += 1;
+            }
+        }
+        assert_eq!(n_onsets, 8);
+    }
+
+    #[test]
+    fn next_from_converts_the_input_format() {
+        let mut from = EnvelopeDetector::<[f32; 2], _>::rms(16, 4.0, 30.0);
+        let mut reference = from.clone();
+        for i in 0..500 {
+            let x = [
+                ((i as f32 * 0.1).sin() * 20_000.0) as i16,
+                ((i as f32 * 0.03).cos() * 9000.0) as i16,
+            ];
+            let f = [x[0] as f32 / 32_768.0, x[1] as f32 / 32_768.0];
+            let env = if i % 2 == 0 { from.next_from(x) } else { from.next_from(f) };
+            assert_eq!(env, reference.next(f));
+        }
+    }
+
+    #[test]
+    fn last_phase_reports_the_direction_of_the_loudest_change() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(10.0, 50.0);
+        assert_eq!(detector.last_phase(), Phase::Steady);
+        detector.next_with_phase([0.0, 0.0]);
+        assert_eq!(detector.last_phase(), Phase::Steady);
+        detector.next_with_phase([0.0, 0.8]);
+        assert_eq!(detector.last_phase(), Phase::Attack);
+        for _ in 0..2000 {
+            detector.next_with_phase([0.0, 0.8]);
+        }
+        assert_eq!(detector.last_phase(), Phase::Steady);
+        assert_eq!(detector.next_with_phase([0.0, 0.0]).1, Phase::Release);
+        detector.reset();
+        assert_eq!(detector.last_phase(), Phase::Steady);
+        detector.next([1.0, 1.0]);
+        assert_eq!(detector.last_phase(), Phase::Attack);
+        let mut frame = [0.0, 0.0];
+        detector.next_in_place(&mut frame);
+        assert_eq!(detector.last_phase(), Phase::Release);
+    }
+
+    #[test]
+    fn per_channel_times_apply_to_their_own_channel() {
+        let mut detector = EnvelopeDetector::with_frames_per_channel(
+            Peak::full_wave(), [0.0f32, 0.0], [100.0, 1000.0]);
+        assert_eq!(detector.channel_release_frames(0), 100.0);
+        assert_eq!(detector.channel_release_frames(1), 1000.0);
+        detector.reset_to([1.0, 1.0]);
+        let mut env = [0.0; 2];
+        for _ in 0..100 {
+            env = detector.next([0.0, 0.0]);
+        }
+        assert!((env[0] - (-1.0f32).exp()).abs() < 1e-4, "{:?}", env);
+        assert!((env[1] - (-0.1f32).exp()).abs() < 1e-4, "{:?}", env);
+        detector.set_release_frames(50.0);
+        assert_eq!(detector.channel_release_frames(1), detector.release_frames());
+    }
+
+    #[test]
+    fn per_channel_attack_or_release_alone_keeps_the_shared_other() {
+        let mut attack_only = EnvelopeDetector::<[f32; 2], _>::peak(0.0, 100.0);
+        attack_only.set_attack_frames_per_channel([10.0, 40.0]);
+        let mut release_only = EnvelopeDetector::<[f32; 2], _>::peak(10.0, 0.0);
+        release_only.set_release_frames_per_channel([100.0, 1000.0]);
+        let mut attack_both = EnvelopeDetector::with_frames_per_channel(
+            Peak::full_wave(), [10.0f32, 40.0], [100.0, 100.0]);
+        let mut release_both = EnvelopeDetector::with_frames_per_channel(
+            Peak::full_wave(), [10.0f32, 10.0], [100.0, 1000.0]);
+        for i in 0..400 {
+            let frame = if (i / 100) % 2 == 0 { [1.0, -0.5] } else { [0.0, 0.0] };
+            assert_eq!(attack_only.next(frame), attack_both.next(frame));
+            assert_eq!(release_only.next(frame), release_both.next(frame));
+        }
+    }
+
+    #[test]
+    fn next_db_decay_falls_at_a_constant_rate() {
+        let sample_hz = 48_000.0;
+        let db = |x: f64| 20.0 * x.abs().log10();
+        let mut detector = EnvelopeDetector::<[f64; 2], _>::peak(1000.0, 1000.0);
+        assert_eq!(detector.next_db_decay([0.5, -0.25], 12.0, sample_hz), [0.5, 0.25]);
+        let mut prev = [db(0.5), db(0.25)];
+        for _ in 0..100_000 {
+            let env = detector.next_db_decay([0.0, 0.0], 12.0, sample_hz);
+            for c in 0..2 {
+                let now = db(env[c]);
+                assert!((prev[c] - now - 12.0 / sample_hz).abs() < 1e-9, "{} {}", prev[c], now);
+                prev[c] = now;
+            }
+        }
+        let env = detector.next_db_decay([0.01, 0.9], 12.0, sample_hz);
+        assert_eq!(env[1], 0.9);
+        assert!(env[0] < 0.5);
+    }
+
+    #[test]
+    fn reset_all_resets_every_detector() {
+        let mut bank: Vec<_> = (0..4)
+            .map(|i| EnvelopeDetector::<[f32; 1], _>::rms(8 + i, 1.0, 10.0))
+            .collect();
+        let mut fresh = bank.clone();
+        for i in 0..100 {
+            for detector in &mut bank {
+                detector.next([(i as f32).sin()]);
+            }
+        }
+        reset_all(&mut bank);
+        for (detector, fresh) in bank.iter_mut().zip(&mut fresh) {
+            assert_eq!(detector.current(), fresh.current());
+            for i in 0..20 {
+                let x = [(i as f32 * 0.3).cos()];
+                assert_eq!(detector.next(x), fresh.next(x));
+            }
+        }
+    }
+
+    #[test]
+    fn session_peak_retains_the_loudest_envelope_until_cleared() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(0.0, 100.0);
+        assert_eq!(detector.session_peak(), [0.0, 0.0]);
+        detector.next([0.9, -0.3]);
+        for _ in 0..5000 {
+            detector.next([0.05, 0.01]);
+        }
+        assert_eq!(detector.session_peak(), [0.9, 0.3]);
+        detector.reset();
+        assert_eq!(detector.session_peak(), [0.9, 0.3]);
+        detector.clear_session_peak();
+        assert_eq!(detector.session_peak(), [0.0, 0.0]);
+
+        // Every path that detects a new envelope frame updates the session peak.
+        detector.next_branchless([0.2, 0.0]);
+        assert_eq!(detector.session_peak(), [0.2, 0.0]);
+        detector.next_log_domain([0.0, 0.4]);
+        assert_eq!(detector.session_peak(), [0.2, 0.4]);
+        detector.next_db_decay([0.6, 0.0], 20.0, 48_000.0);
+        assert_eq!(detector.session_peak(), [0.6, 0.4]);
+        assert_eq!(detector.clone_reset().session_peak(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn next_with_rectified_returns_the_detector_input() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(20.0, 200.0);
+        let mut reference = detector;
+        for i in 0..500 {
+            let x = [(i as f32 * 0.1).sin(), -(i as f32 * 0.05).cos()];
+            let (rectified, env) = detector.next_with_rectified(x);
+            assert_eq!(rectified, [x[0].abs(), x[1].abs()]);
+            assert_eq!(env, reference.next(x));
+        }
+    }
+
+    #[test]
+    fn duck_gain_reaches_full_depth_at_full_scale() {
+        let db = |x: f32| 20.0 * x.log10();
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak(0.0, 0.0);
+        assert_eq!(detector.duck_gain([0.0, 0.0], -30.0, 12.0), [1.0, 1.0]);
+        let gain = detector.duck_gain([1.0, 0.01], -30.0, 12.0);
+        assert!((db(gain[0]) + 12.0).abs() < 1e-4, "{:?}", gain);
+        assert_eq!(gain[1], 1.0);
+        assert_eq!(detector.duck_gain([1.0, 1.0], -30.0, 0.0), [1.0, 1.0]);
+        assert_eq!(detector.duck_gain([1.0, 1.0], -30.0, f32::NAN), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn clone_reset_copies_the_settings_but_not_the_state() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::rms(32, 4.0, 20.0);
+        for i in 0..50 {
+            detector.next([(i as f32 * 0.2).sin(), 0.5]);
+        }
+        let mut clone = detector.clone_reset();
+        let mut fresh = EnvelopeDetector::<[f32; 2], _>::rms(32, 4.0, 20.0);
+        assert_eq!(clone, fresh);
+        for i in 0..40 {
+            let x = [(i as f32 * 0.1).cos(), 0.25];
+            assert_eq!(clone.next(x), fresh.next(x));
+        }
+        assert!(detector.current() != [0.0, 0.0]);
+    }
+
+    #[test]
+    fn next_with_clip_flags_full_scale_input() {
+        let mut integer = EnvelopeDetector::<[i16; 2], _>::peak(0.0, 1000.0);
+        assert!(integer.next_with_clip([i16::MAX, 0]).1);
+        assert!(integer.next_with_clip([0, i16::MIN]).1);
+        assert!(!integer.next_with_clip([1000, -2000]).1);
+        let mut float = EnvelopeDetector::<[f32; 1], _>::peak(100.0, 100.0);
+        let (env, clipped) = float.next_with_clip([1.0]);
+        assert!(clipped);
+        assert!(env[0] < 0.1);
+        assert!(float.next_with_clip([-1.5]).1);
+        assert!(!float.next_with_clip([0.999]).1);
+        let mut unsigned = EnvelopeDetector::<[u8; 1], _>::peak(0.0, 0.0);
+        assert!(unsigned.next_with_clip([255]).1);
+        assert!(unsigned.next_with_clip([0]).1);
+        assert!(!unsigned.next_with_clip([128]).1);
+    }
+
+    #[test]
+    fn linear_smoothing_arrives_in_the_given_frames() {
+        let n = 40;
+        let mut detector = EnvelopeDetector::with_smoothing(
+            Peak::full_wave(), n as f32, n as f32, Smoothing::Linear, Smoothing::DbLinear);
+        assert_eq!(detector.attack_smoothing(), Smoothing::Linear);
+        let up: Vec<f32> = (0..2 * n).map(|_| detector.next([1.0f32])[0]).collect();
+        assert!((up[n / 2 - 1] - 0.5).abs() < 1e-5, "{}", up[n / 2 - 1]);
+        assert!(up[n - 2] < 1.0);
+        assert_eq!(up[n], 1.0);
+        let down: Vec<f32> = (0..2 * n).map(|_| detector.next([0.0f32])[0]).collect();
+        assert!((20.0 * down[n / 2 - 1].log10() + 60.0).abs() < 1e-2, "{}", down[n / 2 - 1]);
+        assert_eq!(down[n], 0.0);
+        assert!(down.windows(2).all(|w| w[1] <= w[0]));
+    }
+
+    #[test]
+    fn every_smoothing_combination_steps_monotonically() {
+        let n = 40;
+        let all = [Smoothing::OnePole,
+                   Smoothing::Linear,
+                   Smoothing::DbLinear,
+                   Smoothing::DbPerFrame(3.0)];
+        for &attack in &all {
+            for &release in &all {
+                let mut detector = EnvelopeDetector::with_smoothing(
+                    Peak::full_wave(), n as f32, n as f32, attack, release);
+                let up: Vec<f32> = (0..5 * n).map(|_| detector.next([1.0f32])[0]).collect();
+                assert!(up.windows(2).all(|w| w[1] >= w[0]), "{:?} {:?}", attack, release);
+                assert!(up[5 * n - 1] > 0.99, "{:?} {:?} {}", attack, release, up[5 * n - 1]);
+                let down: Vec<f32> = (0..5 * n).map(|_| detector.next([0.0f32])[0]).collect();
+                assert!(down.windows(2).all(|w| w[1] <= w[0]), "{:?} {:?}", attack, release);
+                assert!(down[5 * n - 1] < 0.01, "{:?} {:?} {}", attack, release, down[5 * n - 1]);
+
+                // `next_branchless` honours the smoothing of each stage.
+                let mut next = detector.clone_reset();
+                let mut branchless = detector.clone_reset();
+                for i in 0..4 * n {
+                    let x = [if i < 2 * n { 1.0f32 } else { 0.0 }];
+                    assert_eq!(branchless.next_branchless(x), next.next(x),
+                               "{:?} {:?} {}", attack, release, i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn db_per_frame_smoothing_matches_the_slew_setter() {
+        assert_eq!(Smoothing::default(), Smoothing::OnePole);
+        let mut setter = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 100.0);
+        setter.set_release_slew_db_per_sec(20.0, 1000.0);
+        assert_eq!(setter.release_smoothing(), Smoothing::DbPerFrame(0.02));
+        let mut smoothing = EnvelopeDetector::with_smoothing(
+            Peak::full_wave(), 0.0, 100.0, Smoothing::OnePole, Smoothing::DbPerFrame(0.02));
+        setter.reset_to([1.0]);
+        smoothing.reset_to([1.0]);
+        for _ in 0..100 {
+            assert_eq!(setter.next([0.0]), smoothing.next([0.0]));
+        }
+        setter.set_release_frames(10.0);
+        assert_eq!(setter.release_smoothing(), Smoothing::OnePole);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_resumes_mid_stream() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::rms(8, 2.0, 20.0);
+        for i in 0..50 {
+            detector.next([(i as f32 * 0.3).sin(), (i as f32 * 0.7).cos()]);
+        }
+        let json = serde_json::to_string(&detector).unwrap();
+        let mut restored: RmsEnvelopeDetector<[f32; 2]> = serde_json::from_str(&json).unwrap();
+        for i in 50..100 {
+            let frame = [(i as f32 * 0.3).sin(), (i as f32 * 0.7).cos()];
+            assert_eq!(restored.next(frame), detector.next(frame));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! An **EnvelopeDetector** that runs ahead of the signal that it detects.
+//!
+//! The primary type of interest in this module is the
+//! [**LookaheadEnvelopeDetector**](./struct.LookaheadEnvelopeDetector).
+
+use {EnvelopeDetector, FullScale, Mode};
+use sample::Frame;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+use std::collections::VecDeque;
+
+
+/// Pairs an **EnvelopeDetector** with a delay line for the dry signal.
+///
+/// The envelope is detected from each incoming frame while the frame itself is delayed by the
+/// lookahead. This allows a gain derived from the envelope (i.e. by a limiter) to be applied to
+/// audio that lines up with it, reacting to transients before they arrive.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>")))]
+pub struct LookaheadEnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    detector: EnvelopeDetector<F, M>,
+    /// The delay line for the dry signal, whose length is the lookahead in frames.
+    ///
+    /// When a new frame is received, the new frame is pushed to the back and the front frame is
+    /// popped. A `VecDeque` is used (as for the **Rms** window) rather than a fixed length ring
+    /// buffer, as the delay line may be resized via `set_lookahead_frames` and may be empty, in
+    /// which case each frame is pushed and popped straight back out without delay. `sample` 0.6
+    /// does not yet provide a ring buffer.
+    delay: VecDeque<F>,
+}
+
+impl<F, M> std::fmt::Debug for LookaheadEnvelopeDetector<F, M>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "LookaheadEnvelopeDetector {{ detector: {:?}, delay: {:?} }}",
+               &self.detector, &self.delay)
+    }
+}
+
+
+impl<F, M> LookaheadEnvelopeDetector<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// Construct a new **LookaheadEnvelopeDetector** that looks ahead by the given number of
+    /// frames.
+    pub fn new(detector: EnvelopeDetector<F, M>, lookahead_frames: usize) -> Self {
+        LookaheadEnvelopeDetector {
+            detector,
+            delay: (0..lookahead_frames).map(|_| F::equilibrium()).collect(),
+        }
+    }
+
+    /// The lookahead (the length of the dry signal delay line) as a number of frames.
+    #[inline]
+    pub fn lookahead_frames(&self) -> usize {
+        self.delay.len()
+    }
+
+    /// Set the lookahead as a number of frames.
+    ///
+    /// If the delay line is shortened, the oldest frames are dropped. If it is lengthened, frames
+    /// at signal equilibrium are inserted ahead of those already delayed.
+    pub fn set_lookahead_frames(&mut self, lookahead_frames: usize) {
+        while self.delay.len() > lookahead_frames {
+            self.delay.pop_front();
+        }
+        while self.delay.len() < lookahead_frames {
+            self.delay.push_front(F::equilibrium());
+        }
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M> {
+        &mut self.detector
+    }
+
+    /// Reset the inner **EnvelopeDetector** and fill the delay line with equilibrium.
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        for frame in &mut self.delay {
+            *frame = F::equilibrium();
+        }
+    }
+
+    /// Given the next input signal frame, return the delayed input frame along with the envelope
+    /// detected from the (non-delayed) input frame.
+    pub fn next(&mut self, frame: F) -> (F, F) {
+        let envelope = self.detector.next(frame);
+        self.delay.push_back(frame);
+        let delayed = self.delay.pop_front().unwrap();
+        (delayed, envelope)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The index of the loudest frame in the given mono signal.
+    fn peak_index(frames: &[[f32; 1]]) -> usize {
+        (0..frames.len()).fold(0, |max, i| if frames[i][0] > frames[max][0] { i } else { max })
+    }
+
+    #[test]
+    fn envelope_leads_the_delayed_signal() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 5.0);
+        let mut lookahead = LookaheadEnvelopeDetector::new(detector, 3);
+        let (delayed, envelope): (Vec<_>, Vec<_>) = (0..8)
+            .map(|i| lookahead.next([if i == 0 { 1.0 } else { 0.0 }]))
+            .unzip();
+        assert_eq!(peak_index(&envelope), 0);
+        assert_eq!(peak_index(&delayed), 3);
+        assert_eq!(delayed[3], [1.0]);
+        assert!(envelope[3][0] < 1.0);
+    }
+
+    #[test]
+    fn zero_lookahead_passes_the_signal_straight_through() {
+        let mut reference = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 5.0);
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 5.0);
+        let mut lookahead = LookaheadEnvelopeDetector::new(detector, 0);
+        for i in 0..8 {
+            let frame = [if i == 0 { 1.0 } else { 0.0 }];
+            assert_eq!(lookahead.next(frame), (frame, reference.next(frame)));
+        }
+    }
+
+    #[test]
+    fn set_lookahead_frames_resizes_the_delay_line() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 5.0);
+        let mut lookahead = LookaheadEnvelopeDetector::new(detector, 2);
+        assert_eq!(lookahead.next([1.0]).0, [0.0]);
+        lookahead.set_lookahead_frames(0);
+        assert_eq!(lookahead.lookahead_frames(), 0);
+        assert_eq!(lookahead.next([0.5]).0, [0.5]);
+        lookahead.set_lookahead_frames(3);
+        let delayed: Vec<_> = (1..5).map(|i| lookahead.next([i as f32]).0).collect();
+        assert_eq!(delayed, vec![[0.0], [0.0], [0.0], [1.0]]);
+    }
+}
@@ -0,0 +1,135 @@
+//! Momentary and short-term loudness measurement in the style of ITU-R BS.1770.
+//!
+//! The primary type of interest in this module is the [**Loudness**](./struct.Loudness).
+
+use {KWeight, Rms};
+use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+/// The length of the momentary loudness window in seconds.
+pub const MOMENTARY_WINDOW_SECS: f64 = 0.4;
+/// The length of the short-term loudness window in seconds.
+pub const SHORT_TERM_WINDOW_SECS: f64 = 3.0;
+
+
+/// Measures the momentary (400 ms) and short-term (3 s) loudness of a signal in LKFS.
+///
+/// The signal is first passed through a **KWeight** filter, after which the mean square of each
+/// channel is tracked over two sliding **Rms** windows. The channel mean squares are summed with
+/// equal weighting and converted to LKFS as `-0.691 + 10 * log10(mean_square)`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize, \
+                                                       <F::Float as Frame>::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>, \
+                                                         <F::Float as Frame>::Float: \
+                                                         Deserialize<'de>")))]
+pub struct Loudness<F>
+    where F: Frame,
+{
+    k_weight: KWeight<F>,
+    momentary: Rms<F::Float>,
+    short_term: Rms<F::Float>,
+}
+
+impl<F> std::fmt::Debug for Loudness<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+          <F::Float as Frame>::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Loudness {{ k_weight: {:?}, momentary: {:?}, short_term: {:?} }}",
+               &self.k_weight, &self.momentary, &self.short_term)
+    }
+}
+
+
+impl<F> Loudness<F>
+    where F: Frame,
+{
+
+    /// Construct a new **Loudness** meter for a signal at the given sample rate.
+    pub fn new(sample_hz: f64) -> Self {
+        let window_frames = |secs: f64| (secs * sample_hz).round() as usize;
+        Loudness {
+            k_weight: KWeight::new(sample_hz),
+            momentary: Rms::new(window_frames(MOMENTARY_WINDOW_SECS)),
+            short_term: Rms::new(window_frames(SHORT_TERM_WINDOW_SECS)),
+        }
+    }
+
+    /// A reference to the **KWeight** pre-filter.
+    pub fn k_weight(&self) -> &KWeight<F> {
+        &self.k_weight
+    }
+
+    /// A reference to the momentary (400 ms) **Rms** window.
+    pub fn momentary(&self) -> &Rms<F::Float> {
+        &self.momentary
+    }
+
+    /// A reference to the short-term (3 s) **Rms** window.
+    pub fn short_term(&self) -> &Rms<F::Float> {
+        &self.short_term
+    }
+
+    /// Clears the **KWeight** filter state and zeroes both **Rms** windows.
+    pub fn reset(&mut self) {
+        self.k_weight.reset();
+        self.momentary.reset();
+        self.short_term.reset();
+    }
+
+    /// Feed the next input signal frame to the meter.
+    pub fn next(&mut self, frame: F) {
+        let frame = self.k_weight.next(frame);
+        self.momentary.next_mean_square(frame);
+        self.short_term.next_mean_square(frame);
+    }
+
+    /// The loudness over the last 400 ms in LKFS.
+    ///
+    /// Yields negative infinity for silence.
+    pub fn momentary_lkfs(&self) -> f32 {
+        lkfs::<F::Float>(self.momentary.mean_square())
+    }
+
+    /// The loudness over the last 3 s in LKFS.
+    ///
+    /// Yields negative infinity for silence.
+    pub fn short_term_lkfs(&self) -> f32 {
+        lkfs::<F::Float>(self.short_term.mean_square())
+    }
+
+}
+
+/// Sum the channel mean squares and convert the result to LKFS.
+fn lkfs<F>(mean_square: F::Float) -> f32
+    where F: Frame,
+{
+    let sum = mean_square.channels().fold(0.0, |sum, s| sum + s.to_sample::<f32>());
+    -0.691 + 10.0 * sum.log10()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_loudness_of_a_calibrated_sine() {
+        use std::f32::consts::PI;
+        let sample_hz = 48_000.0;
+        let mut loudness = Loudness::<[f32; 2]>::new(sample_hz);
+        // A 997 Hz stereo sine whose amplitude reads -23 LKFS once K-weighted.
+        let amp = 10f32.powf(-23.0 / 20.0);
+        for i in 0..(sample_hz as usize * 4) {
+            let x = amp * (2.0 * PI * 997.0 * i as f32 / sample_hz as f32).sin();
+            loudness.next([x, x]);
+        }
+        assert!((loudness.momentary_lkfs() + 23.0).abs() < 0.05, "{}", loudness.momentary_lkfs());
+        assert!((loudness.short_term_lkfs() + 23.0).abs() < 0.05, "{}", loudness.short_term_lkfs());
+    }
+}
@@ -0,0 +1,95 @@
+//! Mapping of envelope levels onto the segments of an LED-style meter.
+//!
+//! The primary type of interest in this module is the [**MeterScale**](./struct.MeterScale).
+
+use sample::Sample;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+
+/// Maps envelope levels onto a number of equally sized meter segments across a range of decibels,
+/// so that a UI may light the segments of an LED-style meter directly.
+///
+/// Levels at or below `min_db` light no segments, while levels at or above `max_db` light them
+/// all. In between, segment `i` (counting from `1`) is lit once the level reaches
+/// `min_db + i * (max_db - min_db) / n_segments`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MeterScale {
+    min_db: f32,
+    max_db: f32,
+    n_segments: usize,
+}
+
+
+impl MeterScale {
+
+    /// Construct a new **MeterScale** of `n_segments` spanning `min_db` to `max_db`, i.e.
+    /// `MeterScale::new(-60.0, 0.0, 12)` for a twelve segment meter with 5 dB per segment.
+    ///
+    /// **Panics** if `min_db` is not less than `max_db`.
+    pub fn new(min_db: f32, max_db: f32, n_segments: usize) -> Self {
+        assert!(min_db < max_db, "`min_db` must be less than `max_db`");
+        MeterScale {
+            min_db,
+            max_db,
+            n_segments,
+        }
+    }
+
+    /// The level in decibels at or below which no segments are lit.
+    pub fn min_db(&self) -> f32 {
+        self.min_db
+    }
+
+    /// The level in decibels at or above which all segments are lit.
+    pub fn max_db(&self) -> f32 {
+        self.max_db
+    }
+
+    /// The total number of segments.
+    pub fn n_segments(&self) -> usize {
+        self.n_segments
+    }
+
+    /// The number of segments lit by the given linear envelope value, in the range
+    /// `0..=n_segments`.
+    ///
+    /// The magnitude of the envelope is converted to decibels relative to full scale, so this
+    /// accepts the envelope in any sample format (i.e. a channel of `F::Float`).
+    pub fn linear_to_segments<S>(&self, env: S) -> usize
+        where S: Sample,
+    {
+        let amp = env.to_float_sample().to_sample::<f32>().abs();
+        let db = ::amp_to_db(amp, self.min_db);
+        if db >= self.max_db {
+            return self.n_segments;
+        }
+        let segment_db = (self.max_db - self.min_db) / self.n_segments as f32;
+        let lit = ((db - self.min_db) / segment_db) as usize;
+        if lit > self.n_segments { self.n_segments } else { lit }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_one_segment_per_step_of_the_scale() {
+        let meter = MeterScale::new(-60.0, 0.0, 12);
+        let amp = |db: f32| 10f32.powf(db / 20.0);
+        assert_eq!(meter.linear_to_segments(1.0f32), 12);
+        assert_eq!(meter.linear_to_segments(-1.0f64), 12);
+        assert_eq!(meter.linear_to_segments(amp(-60.0)), 0);
+        assert_eq!(meter.linear_to_segments(0.0f32), 0);
+        for segment in 1..12 {
+            let db = -60.0 + 5.0 * segment as f32;
+            assert_eq!(meter.linear_to_segments(amp(db + 0.01)), segment);
+            assert_eq!(meter.linear_to_segments(amp(db - 0.01)), segment - 1);
+        }
+        assert_eq!(meter.linear_to_segments(i16::MIN), 12);
+    }
+}
@@ -0,0 +1,120 @@
+//! Simultaneous upper and lower envelope detection over a signal.
+//!
+//! The primary type of interest in this module is the
+//! [**MinMaxDetector**](./struct.MinMaxDetector).
+
+use {EnvelopeDetector, FullScale, Peak};
+use peak::{NegativeHalfWave, PositiveHalfWave};
+use sample::Frame;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// Detects both the upper (positive peak) and lower (negative peak) envelopes of a signal in a
+/// single pass, i.e. for drawing a filled waveform.
+///
+/// Both envelopes share the same attack and release.
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>")))]
+pub struct MinMaxDetector<F>
+    where F: Frame,
+{
+    upper: EnvelopeDetector<F, Peak<PositiveHalfWave>>,
+    lower: EnvelopeDetector<F, Peak<NegativeHalfWave>>,
+}
+
+impl<F> std::fmt::Debug for MinMaxDetector<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "MinMaxDetector {{ upper: {:?}, lower: {:?} }}", &self.upper, &self.lower)
+    }
+}
+
+
+impl<F> MinMaxDetector<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **MinMaxDetector** with the given attack and release times as a number of
+    /// frames.
+    pub fn new(attack_frames: f32, release_frames: f32) -> Self {
+        MinMaxDetector {
+            upper: EnvelopeDetector::new(Peak::positive_half_wave(), attack_frames, release_frames),
+            lower: EnvelopeDetector::new(Peak::negative_half_wave(), attack_frames, release_frames),
+        }
+    }
+
+    /// Set the attack time of both envelopes as a number of frames.
+    pub fn set_attack_frames(&mut self, frames: f32) {
+        self.upper.set_attack_frames(frames);
+        self.lower.set_attack_frames(frames);
+    }
+
+    /// Set the release time of both envelopes as a number of frames.
+    pub fn set_release_frames(&mut self, frames: f32) {
+        self.upper.set_release_frames(frames);
+        self.lower.set_release_frames(frames);
+    }
+
+    /// The attack time of both envelopes as a number of frames.
+    pub fn attack_frames(&self) -> f32 {
+        self.upper.attack_frames()
+    }
+
+    /// The release time of both envelopes as a number of frames.
+    pub fn release_frames(&self) -> f32 {
+        self.upper.release_frames()
+    }
+
+    /// A reference to the upper (positive half wave) **EnvelopeDetector**.
+    pub fn upper(&self) -> &EnvelopeDetector<F, Peak<PositiveHalfWave>> {
+        &self.upper
+    }
+
+    /// A reference to the lower (negative half wave) **EnvelopeDetector**.
+    pub fn lower(&self) -> &EnvelopeDetector<F, Peak<NegativeHalfWave>> {
+        &self.lower
+    }
+
+    /// Reset both envelopes to equilibrium.
+    pub fn reset(&mut self) {
+        self.upper.reset();
+        self.lower.reset();
+    }
+
+    /// Given the next input signal frame, detect and return the next `(upper, lower)` envelope
+    /// frames.
+    ///
+    /// The upper envelope is at or above equilibrium while the lower envelope is at or below it.
+    pub fn next(&mut self, frame: F) -> (F, F) {
+        (self.upper.next(frame), self.lower.next(frame))
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upper_and_lower_envelopes_mirror_each_other() {
+        let mut detector = MinMaxDetector::<[f32; 1]>::new(0.0, 20.0);
+        let mut inverted = detector;
+        for i in 0..1000 {
+            let x = (i as f32 * 0.05).sin();
+            let (upper, lower) = detector.next([x]);
+            let (inverted_upper, inverted_lower) = inverted.next([-x]);
+            assert!(upper[0] >= 0.0 && lower[0] <= 0.0);
+            assert_eq!(upper[0], -inverted_lower[0]);
+            assert_eq!(lower[0], -inverted_upper[0]);
+        }
+    }
+}
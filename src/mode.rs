@@ -1,11 +1,20 @@
-//! A generic interface over the two kinds of detection modes currently available to the
+//! A generic interface over the kinds of detection modes currently available to the
 //! **EnvelopeDetector**.
 //!
-//! See the [**Mode**](./trait.Mode) trait.
+//! See the [**Mode**](./trait.Mode) trait along with the [**PeakHold**](./struct.PeakHold),
+//! [**WindowedPeak**](./struct.WindowedPeak) and
+//! [**ProgramDependentRelease**](./struct.ProgramDependentRelease) modes.
+//!
+//! The [**AnyMode**](./enum.AnyMode) type may be used to select between modes at runtime.
 
-use peak::{self, Peak};
-use rms::Rms;
+use peak::{self, DynPeak, Peak, Rectifier, TruePeak};
+use rms::{ArrayRms, ExpRms, Rms};
 use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+use std::collections::VecDeque;
+use {Ballistics, FullScale};
 
 
 /// The mode used to detect the envelope of a signal.
@@ -14,6 +23,639 @@ pub trait Mode<F>
 {
     /// Update state that is unique to the **Mode**.
     fn next_frame(&mut self, frame: F) -> F;
+    /// The same as `next_frame`, but producing the frame in the floating point representation of
+    /// `F`.
+    ///
+    /// By default this converts the result of `next_frame`. Modes that calculate their result in
+    /// floating point (i.e. **Rms**) return it before it is converted to `F`.
+    fn next_float_frame(&mut self, frame: F) -> F::Float {
+        self.next_frame(frame).to_float_frame()
+    }
+    /// Whether the **EnvelopeDetector** should smooth the frames produced by `next_float_frame`
+    /// in floating point, converting the envelope to `F` only once it has been smoothed.
+    ///
+    /// By default this is `false`, in which case the envelope is smoothed in `F` exactly as
+    /// though the detected frames were produced by `next_frame`. Modes that calculate their
+    /// result in floating point (i.e. **Rms**) return `true`, so that for integer frames the
+    /// result is not truncated before smoothing.
+    fn smooths_in_float(&self) -> bool {
+        false
+    }
+    /// Whether the frames produced by the **Mode** have already been released, in which case the
+    /// **EnvelopeDetector** follows them directly whenever they fall, applying only its attack.
+    ///
+    /// By default this is `false`. Modes that apply a release of their own (i.e.
+    /// **ProgramDependentRelease**) return `true`, so that the release of the detector is not
+    /// compounded with it.
+    fn applies_release(&self) -> bool {
+        false
+    }
+    /// Clear any state that is unique to the **Mode**.
+    ///
+    /// By default this does nothing, as stateless modes (i.e. **Peak**) have nothing to clear.
+    fn reset(&mut self) {}
+    /// Rescale any durations held by the **Mode** (i.e. the **Rms** window length) following a
+    /// change in sample rate, where `ratio` is the new sample rate divided by the old.
+    ///
+    /// Implementations should rescale each duration from its exact length rather than from a
+    /// rounded one, so that repeated calls do not accumulate rounding error.
+    ///
+    /// By default this does nothing, as modes without durations (i.e. **Peak**) have nothing to
+    /// rescale.
+    fn retune(&mut self, _ratio: f64) {}
+}
+
+/// The **Mode** trait is object safe, however in order to switch between modes at runtime without
+/// boxing, this type may be used.
+///
+/// **AnyMode** dispatches to whichever of the crate's detection modes it currently holds.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>")))]
+pub enum AnyMode<F>
+    where F: Frame,
+{
+    /// Full wave **Peak** detection.
+    Peak(Peak<peak::FullWave>),
+    /// Windowed **Rms** detection.
+    Rms(Rms<F>),
+    /// Exponentially weighted **ExpRms** detection.
+    ExpRms(ExpRms<F>),
+    /// Full wave **PeakHold** detection.
+    PeakHold(PeakHold<F>),
+}
+
+impl<F> std::fmt::Debug for AnyMode<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            AnyMode::Peak(ref peak) => write!(f, "AnyMode::Peak({:?})", peak),
+            AnyMode::Rms(ref rms) => write!(f, "AnyMode::Rms({:?})", rms),
+            AnyMode::ExpRms(ref exp_rms) => write!(f, "AnyMode::ExpRms({:?})", exp_rms),
+            AnyMode::PeakHold(ref peak_hold) => write!(f, "AnyMode::PeakHold({:?})", peak_hold),
+        }
+    }
+}
+
+impl<F> PartialEq for AnyMode<F>
+    where F: Frame + PartialEq,
+          F::Float: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (AnyMode::Peak(a), AnyMode::Peak(b)) => a == b,
+            (AnyMode::Rms(a), AnyMode::Rms(b)) => a == b,
+            (AnyMode::ExpRms(a), AnyMode::ExpRms(b)) => a == b,
+            (AnyMode::PeakHold(a), AnyMode::PeakHold(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<F> AnyMode<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    /// Seed the state of the mode as though it had been receiving a steady signal at the level of
+    /// the given envelope.
+    pub(crate) fn seed(&mut self, envelope: F) {
+        let level = peak::FullWave::rectify(envelope).to_float_frame();
+        match *self {
+            AnyMode::Rms(ref mut rms) => rms.seed(level),
+            AnyMode::ExpRms(ref mut exp_rms) => exp_rms.seed(level),
+            AnyMode::Peak(_) | AnyMode::PeakHold(_) => (),
+        }
+    }
+}
+
+impl<F> From<Peak<peak::FullWave>> for AnyMode<F>
+    where F: Frame,
+{
+    fn from(peak: Peak<peak::FullWave>) -> Self {
+        AnyMode::Peak(peak)
+    }
+}
+
+impl<F> From<Rms<F>> for AnyMode<F>
+    where F: Frame,
+{
+    fn from(rms: Rms<F>) -> Self {
+        AnyMode::Rms(rms)
+    }
+}
+
+impl<F> From<ExpRms<F>> for AnyMode<F>
+    where F: Frame,
+{
+    fn from(exp_rms: ExpRms<F>) -> Self {
+        AnyMode::ExpRms(exp_rms)
+    }
+}
+
+impl<F> From<PeakHold<F>> for AnyMode<F>
+    where F: Frame,
+{
+    fn from(peak_hold: PeakHold<F>) -> Self {
+        AnyMode::PeakHold(peak_hold)
+    }
+}
+
+impl<F> Mode<F> for AnyMode<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        match *self {
+            AnyMode::Peak(ref mut peak) => peak.next_frame(frame),
+            AnyMode::Rms(ref mut rms) => rms.next_frame(frame),
+            AnyMode::ExpRms(ref mut exp_rms) => exp_rms.next_frame(frame),
+            AnyMode::PeakHold(ref mut peak_hold) => peak_hold.next_frame(frame),
+        }
+    }
+
+    fn next_float_frame(&mut self, frame: F) -> F::Float {
+        match *self {
+            AnyMode::Peak(ref mut peak) => Mode::<F>::next_float_frame(peak, frame),
+            AnyMode::Rms(ref mut rms) => Mode::<F>::next_float_frame(rms, frame),
+            AnyMode::ExpRms(ref mut exp_rms) => Mode::<F>::next_float_frame(exp_rms, frame),
+            AnyMode::PeakHold(ref mut peak_hold) => peak_hold.next_float_frame(frame),
+        }
+    }
+
+    fn smooths_in_float(&self) -> bool {
+        match *self {
+            AnyMode::Peak(ref peak) => Mode::<F>::smooths_in_float(peak),
+            AnyMode::Rms(ref rms) => Mode::<F>::smooths_in_float(rms),
+            AnyMode::ExpRms(ref exp_rms) => Mode::<F>::smooths_in_float(exp_rms),
+            AnyMode::PeakHold(ref peak_hold) => peak_hold.smooths_in_float(),
+        }
+    }
+
+    fn reset(&mut self) {
+        match *self {
+            AnyMode::Peak(ref mut peak) => Mode::<F>::reset(peak),
+            AnyMode::Rms(ref mut rms) => Mode::<F>::reset(rms),
+            AnyMode::ExpRms(ref mut exp_rms) => Mode::<F>::reset(exp_rms),
+            AnyMode::PeakHold(ref mut peak_hold) => Mode::<F>::reset(peak_hold),
+        }
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        match *self {
+            AnyMode::Peak(ref mut peak) => Mode::<F>::retune(peak, ratio),
+            AnyMode::Rms(ref mut rms) => Mode::<F>::retune(rms, ratio),
+            AnyMode::ExpRms(ref mut exp_rms) => Mode::<F>::retune(exp_rms, ratio),
+            AnyMode::PeakHold(ref mut peak_hold) => Mode::<F>::retune(peak_hold, ratio),
+        }
+    }
+}
+
+
+/// A full wave peak mode that holds each new maximum for some number of frames.
+///
+/// Once the hold period has expired the rectified signal is passed through as normal, allowing
+/// the **EnvelopeDetector**'s release to take over. The hold period is restarted whenever a new
+/// maximum arrives on any channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PeakHold<F> {
+    /// The peak that is currently being held.
+    held: F,
+    /// The number of frames for which each new maximum is held.
+    hold_frames: usize,
+    /// The hold length in frames before rounding to `hold_frames`, from which `retune` rescales
+    /// so that repeated calls do not accumulate rounding error.
+    exact_hold_frames: f64,
+    /// The number of frames remaining in the current hold period.
+    remaining: usize,
+}
+
+impl<F> PeakHold<F>
+    where F: Frame,
+{
+    /// Construct a new **PeakHold** that holds each new maximum for `hold_frames`, counting the
+    /// frame on which it arrives.
+    pub fn new(hold_frames: usize) -> Self {
+        PeakHold {
+            held: F::equilibrium(),
+            hold_frames,
+            exact_hold_frames: hold_frames as f64,
+            remaining: 0,
+        }
+    }
+
+    /// The number of frames for which each new maximum is held.
+    pub fn hold_frames(&self) -> usize {
+        self.hold_frames
+    }
+
+    /// Set the number of frames for which each new maximum is held.
+    ///
+    /// This takes effect when the next maximum arrives.
+    pub fn set_hold_frames(&mut self, hold_frames: usize) {
+        self.hold_frames = hold_frames;
+        self.exact_hold_frames = hold_frames as f64;
+    }
+}
+
+impl<F> Mode<F> for PeakHold<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        let rectified = peak::FullWave::rectify(frame);
+        let mut is_new_max = false;
+        self.held = self.held.zip_map(rectified, |h, r| {
+            if r > h { is_new_max = true; r } else { h }
+        });
+        if is_new_max {
+            // The frame on which the new maximum arrives is the first frame of the hold.
+            self.remaining = self.hold_frames.saturating_sub(1);
+        } else if self.remaining > 0 {
+            self.remaining -= 1;
+        } else {
+            self.held = rectified;
+        }
+        self.held
+    }
+
+    fn reset(&mut self) {
+        self.held = F::equilibrium();
+        self.remaining = 0;
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        self.exact_hold_frames = ::retune_frames(self.exact_hold_frames, ratio);
+        self.hold_frames = self.exact_hold_frames.round() as usize;
+    }
+}
+
+/// A full wave peak mode that holds each new maximum and then falls back at a fixed rate in
+/// decibels per second, as in a Peak Programme Meter.
+///
+/// Once the hold period has expired, the held peak decreases linearly in dB (rather than the
+/// exponential release of the **EnvelopeDetector**) until it meets the rectified signal. The
+/// hold period is restarted whenever a new maximum arrives on any channel.
+///
+/// For pure PPM ballistics, use this mode with an instantaneous release.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HoldRelease<F> {
+    /// The peak that is currently being held or falling back.
+    held: F,
+    /// The number of frames for which each new maximum is held.
+    hold_frames: usize,
+    /// The hold length in frames before rounding to `hold_frames`, from which `retune` rescales
+    /// so that repeated calls do not accumulate rounding error.
+    exact_hold_frames: f64,
+    /// The number of frames remaining in the current hold period.
+    remaining: usize,
+    /// The fall back rate in decibels per frame.
+    fallback_db_per_frame: f64,
+    /// The linear gain applied to the held peak each frame once the hold period has expired.
+    fallback_gain: f32,
+}
+
+impl<F> HoldRelease<F>
+    where F: Frame,
+{
+    /// Construct a new **HoldRelease** that holds each new maximum for `hold_frames` (counting the
+    /// frame on which it arrives) before falling back at `fallback_db_per_sec` for a signal at the
+    /// given sample rate.
+    pub fn new(hold_frames: usize, fallback_db_per_sec: f32, sample_hz: f64) -> Self {
+        let mut hold_release = HoldRelease {
+            held: F::equilibrium(),
+            hold_frames,
+            exact_hold_frames: hold_frames as f64,
+            remaining: 0,
+            fallback_db_per_frame: 0.0,
+            fallback_gain: 1.0,
+        };
+        hold_release.set_fallback_db_per_sec(fallback_db_per_sec, sample_hz);
+        hold_release
+    }
+
+    /// The number of frames for which each new maximum is held.
+    pub fn hold_frames(&self) -> usize {
+        self.hold_frames
+    }
+
+    /// Set the number of frames for which each new maximum is held.
+    ///
+    /// This takes effect when the next maximum arrives.
+    pub fn set_hold_frames(&mut self, hold_frames: usize) {
+        self.hold_frames = hold_frames;
+        self.exact_hold_frames = hold_frames as f64;
+    }
+
+    /// The fall back rate in decibels per frame.
+    pub fn fallback_db_per_frame(&self) -> f32 {
+        self.fallback_db_per_frame as f32
+    }
+
+    /// Set the fall back rate in decibels per second for a signal at the given sample rate.
+    ///
+    /// Negative and NaN rates are treated as `0.0` (the peak is held until a new maximum).
+    pub fn set_fallback_db_per_sec(&mut self, fallback_db_per_sec: f32, sample_hz: f64) {
+        let db_per_sec = if fallback_db_per_sec > 0.0 { fallback_db_per_sec as f64 } else { 0.0 };
+        self.fallback_db_per_frame = db_per_sec / sample_hz;
+        self.fallback_gain = ::db_to_amp(-self.fallback_db_per_frame as f32);
+    }
+}
+
+impl<F> Mode<F> for HoldRelease<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        let rectified = peak::FullWave::rectify(frame);
+        let mut is_new_max = false;
+        self.held = self.held.zip_map(rectified, |h, r| {
+            if r > h { is_new_max = true; r } else { h }
+        });
+        if is_new_max {
+            // The frame on which the new maximum arrives is the first frame of the hold.
+            self.remaining = self.hold_frames.saturating_sub(1);
+        } else if self.remaining > 0 {
+            self.remaining -= 1;
+        } else {
+            let fallback_gain = self.fallback_gain.to_sample();
+            self.held = self.held.zip_map(rectified, |h, r| {
+                let h = h.mul_amp(fallback_gain);
+                if r > h { r } else { h }
+            });
+        }
+        self.held
+    }
+
+    fn reset(&mut self) {
+        self.held = F::equilibrium();
+        self.remaining = 0;
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        self.exact_hold_frames = ::retune_frames(self.exact_hold_frames, ratio);
+        self.hold_frames = self.exact_hold_frames.round() as usize;
+        self.fallback_db_per_frame /= ratio;
+        self.fallback_gain = ::db_to_amp(-self.fallback_db_per_frame as f32);
+    }
+}
+
+/// A full wave peak mode with a program-dependent (or "auto") release that blends between a fast
+/// and a slow release depending on how long the signal has been sustained.
+///
+/// The signal is considered sustained for as long as it keeps reaching the envelope on any
+/// channel within `fast_release_frames` of last doing so. The release gain is blended from the
+/// gain of the fast towards the gain of the slow release time in proportion to the number of
+/// frames sustained, reaching the slow release time once the signal has been sustained for
+/// `slow_release_frames`. Short transients therefore release quickly, while long sustained
+/// material releases slowly. A new period of sustain begins once the signal reaches the envelope
+/// after having fallen below it for longer than `fast_release_frames`.
+///
+/// The gains for the fast and slow release times are calculated whenever either time is set,
+/// so no gain needs to be calculated per frame. As the blend is linear in the gain, the release
+/// time stays close to the fast release time until the sustain nears `slow_release_frames`.
+///
+/// The attack is instantaneous, so set the attack of the **EnvelopeDetector** as usual. Its
+/// release however is **bypassed** (see **Mode::applies_release**): the envelope falls only at
+/// the release of this mode, regardless of the release time, per-channel release times or
+/// release smoothing set on the **EnvelopeDetector**.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProgramDependentRelease<F> {
+    /// The envelope that is currently being held or released.
+    held: F,
+    /// The release time in frames for short transients, kept at `f64` precision so that `retune`
+    /// may rescale it repeatedly without accumulating rounding error.
+    fast_release_frames: f64,
+    /// The release time in frames for sustained material, kept at `f64` precision as above.
+    slow_release_frames: f64,
+    /// The release gain for `fast_release_frames`.
+    fast_release_gain: f64,
+    /// The release gain for `slow_release_frames`.
+    slow_release_gain: f64,
+    /// The number of frames for which the signal has been sustained.
+    sustained: usize,
+    /// The number of frames since the signal last reached the envelope.
+    since_reached: usize,
+}
+
+impl<F> ProgramDependentRelease<F>
+    where F: Frame,
+{
+    /// Construct a new **ProgramDependentRelease** that releases short transients over
+    /// `fast_release_frames` and sustained material over `slow_release_frames`.
+    pub fn new(fast_release_frames: f32, slow_release_frames: f32) -> Self {
+        ProgramDependentRelease {
+            held: F::equilibrium(),
+            fast_release_frames: fast_release_frames as f64,
+            slow_release_frames: slow_release_frames as f64,
+            fast_release_gain: Ballistics::Digital.gain(fast_release_frames as f64),
+            slow_release_gain: Ballistics::Digital.gain(slow_release_frames as f64),
+            sustained: 0,
+            since_reached: 0,
+        }
+    }
+
+    /// The release time in frames for short transients.
+    pub fn fast_release_frames(&self) -> f32 {
+        self.fast_release_frames as f32
+    }
+
+    /// Set the release time in frames for short transients.
+    pub fn set_fast_release_frames(&mut self, frames: f32) {
+        self.fast_release_frames = frames as f64;
+        self.fast_release_gain = Ballistics::Digital.gain(frames as f64);
+    }
+
+    /// The release time in frames for sustained material.
+    pub fn slow_release_frames(&self) -> f32 {
+        self.slow_release_frames as f32
+    }
+
+    /// Set the release time in frames for sustained material.
+    pub fn set_slow_release_frames(&mut self, frames: f32) {
+        self.slow_release_frames = frames as f64;
+        self.slow_release_gain = Ballistics::Digital.gain(frames as f64);
+    }
+
+    /// The release gain blended between the fast and slow release gains by the current sustain.
+    fn release_gain(&self) -> f64 {
+        let sustain = if self.slow_release_frames > 0.0 {
+            let sustain = self.sustained as f64 / self.slow_release_frames;
+            if sustain < 1.0 { sustain } else { 1.0 }
+        } else {
+            1.0
+        };
+        self.fast_release_gain + (self.slow_release_gain - self.fast_release_gain) * sustain
+    }
+}
+
+impl<F> Mode<F> for ProgramDependentRelease<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        let rectified = peak::FullWave::rectify(frame);
+        let mut is_reached = false;
+        for (h, r) in self.held.channels().zip(rectified.channels()) {
+            if r >= h && r != Sample::equilibrium() {
+                is_reached = true;
+            }
+        }
+        if is_reached {
+            // The frames since the signal last reached the envelope only count towards the
+            // sustain if the signal returned within the fast release time.
+            if self.since_reached as f64 > self.fast_release_frames {
+                self.sustained = 1;
+            } else {
+                let elapsed = self.since_reached.saturating_add(1);
+                self.sustained = self.sustained.saturating_add(elapsed);
+            }
+            self.since_reached = 0;
+        } else {
+            self.since_reached = self.since_reached.saturating_add(1);
+        }
+        let gain = self.release_gain();
+        self.held = self.held.zip_map(rectified, |h, r| {
+            if r > h { r } else { ::apply_gain(h, r, gain) }
+        });
+        self.held
+    }
+
+    fn applies_release(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.held = F::equilibrium();
+        self.sustained = 0;
+        self.since_reached = 0;
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        self.fast_release_frames = ::retune_frames(self.fast_release_frames, ratio);
+        self.slow_release_frames = ::retune_frames(self.slow_release_frames, ratio);
+        self.fast_release_gain = Ballistics::Digital.gain(self.fast_release_frames);
+        self.slow_release_gain = Ballistics::Digital.gain(self.slow_release_frames);
+    }
+}
+
+/// A full wave peak mode that produces the maximum of each channel over the last `window_frames`
+/// frames.
+///
+/// Unlike the exponential release of the **EnvelopeDetector**, this guarantees that no peak
+/// within the window is underreported, making it suitable for look-back limiting.
+///
+/// Each channel maintains a monotonic deque of candidate maxima, so that the sliding maximum is
+/// found in O(1) amortized time per frame regardless of the window length.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Sample: Serialize",
+                                          deserialize = "F::Sample: Deserialize<'de>")))]
+pub struct WindowedPeak<F>
+    where F: Frame,
+{
+    /// For each channel, the index and rectified value of each frame that may yet become the
+    /// maximum of the window, in order of arrival and with decreasing values.
+    candidates: Vec<VecDeque<(usize, F::Sample)>>,
+    /// The number of frames over which the maximum is found.
+    window_frames: usize,
+    /// The window length in frames before rounding to `window_frames`, from which `retune`
+    /// rescales so that repeated calls do not accumulate rounding error.
+    exact_window_frames: f64,
+    /// The index of the next frame, wrapping on overflow.
+    frame_index: usize,
+}
+
+impl<F> std::fmt::Debug for WindowedPeak<F>
+    where F: Frame,
+          F::Sample: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "WindowedPeak {{ candidates: {:?}, window_frames: {:?}, \
+                   exact_window_frames: {:?}, frame_index: {:?} }}",
+               &self.candidates, &self.window_frames, &self.exact_window_frames,
+               &self.frame_index)
+    }
+}
+
+impl<F> WindowedPeak<F>
+    where F: Frame,
+{
+    /// Construct a new **WindowedPeak** that produces the maximum over the last `window_frames`.
+    ///
+    /// A `window_frames` of `0` is treated as `1`, in which case the rectified signal is passed
+    /// straight through.
+    pub fn new(window_frames: usize) -> Self {
+        let window_frames = if window_frames > 1 { window_frames } else { 1 };
+        WindowedPeak {
+            candidates: (0..F::n_channels())
+                .map(|_| VecDeque::with_capacity(window_frames))
+                .collect(),
+            window_frames,
+            exact_window_frames: window_frames as f64,
+            frame_index: 0,
+        }
+    }
+
+    /// The number of frames over which the maximum is found.
+    pub fn window_frames(&self) -> usize {
+        self.window_frames
+    }
+
+    /// Set the number of frames over which the maximum is found.
+    ///
+    /// A `window_frames` of `0` is treated as `1`. When shortening the window, peaks older than
+    /// the new window expire on the next frame.
+    pub fn set_window_frames(&mut self, window_frames: usize) {
+        self.window_frames = if window_frames > 1 { window_frames } else { 1 };
+        self.exact_window_frames = self.window_frames as f64;
+    }
+}
+
+impl<F> Mode<F> for WindowedPeak<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        let rectified = peak::FullWave::rectify(frame);
+        let WindowedPeak { ref mut candidates, window_frames, frame_index, .. } = *self;
+        self.frame_index = frame_index.wrapping_add(1);
+        F::from_fn(|channel| {
+            let candidates = &mut candidates[channel];
+            let value = *rectified.channel(channel).unwrap();
+            // Any candidate no greater than the new value can never again be the maximum.
+            while candidates.back().is_some_and(|&(_, v)| v <= value) {
+                candidates.pop_back();
+            }
+            candidates.push_back((frame_index, value));
+            // Expire the candidate that has fallen out of the window, if any.
+            while candidates.front()
+                .is_some_and(|&(i, _)| frame_index.wrapping_sub(i) >= window_frames)
+            {
+                candidates.pop_front();
+            }
+            candidates.front().unwrap().1
+        })
+    }
+
+    fn reset(&mut self) {
+        for candidates in &mut self.candidates {
+            candidates.clear();
+        }
+        self.frame_index = 0;
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        let exact_window_frames = ::retune_frames(self.exact_window_frames, ratio);
+        self.set_window_frames(exact_window_frames.round() as usize);
+        self.exact_window_frames = exact_window_frames;
+    }
 }
 
 impl<F, R> Mode<F> for Peak<R>
@@ -25,10 +667,229 @@ impl<F, R> Mode<F> for Peak<R>
     }
 }
 
+impl<F> Mode<F> for DynPeak
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        self.rectify(frame)
+    }
+}
+
+impl<F> Mode<F> for TruePeak<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        self.rectify(frame)
+    }
+
+    fn reset(&mut self) {
+        TruePeak::reset(self);
+    }
+}
+
 impl<F> Mode<F> for Rms<F>
     where F: Frame,
 {
     fn next_frame(&mut self, frame: F) -> F {
         self.next(frame).map(|s| s.to_sample::<F::Sample>())
     }
+    fn next_float_frame(&mut self, frame: F) -> F::Float {
+        self.next(frame)
+    }
+    fn smooths_in_float(&self) -> bool {
+        true
+    }
+    fn reset(&mut self) {
+        Rms::reset(self);
+    }
+    fn retune(&mut self, ratio: f64) {
+        Rms::retune(self, ratio);
+    }
+}
+
+impl<F, const N: usize> Mode<F> for ArrayRms<F, N>
+    where F: Frame,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        self.next(frame).map(|s| s.to_sample::<F::Sample>())
+    }
+    fn next_float_frame(&mut self, frame: F) -> F::Float {
+        self.next(frame)
+    }
+    fn smooths_in_float(&self) -> bool {
+        true
+    }
+    fn reset(&mut self) {
+        Rms::reset(self);
+    }
+    // The window length is fixed by the type, so it is not rescaled by `retune`.
+}
+
+impl<F> Mode<F> for ExpRms<F>
+    where F: Frame,
+{
+    fn next_frame(&mut self, frame: F) -> F {
+        self.next(frame).map(|s| s.to_sample::<F::Sample>())
+    }
+    fn next_float_frame(&mut self, frame: F) -> F::Float {
+        self.next(frame)
+    }
+    fn smooths_in_float(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        ExpRms::reset(self);
+    }
+
+    fn retune(&mut self, ratio: f64) {
+        ExpRms::retune(self, ratio);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EnvelopeDetector;
+
+    /// The rectified output of the **Mode** for a single full scale impulse followed by silence.
+    fn impulse_response<M>(mode: &mut M, n_frames: usize) -> Vec<f32>
+        where M: Mode<[f32; 1]>,
+    {
+        (0..n_frames).map(|i| mode.next_frame([if i == 0 { 1.0 } else { 0.0 }])[0]).collect()
+    }
+
+    #[test]
+    fn peak_hold_holds_for_exactly_hold_frames() {
+        for &hold_frames in &[1, 3, 5, 10] {
+            let response = impulse_response(&mut PeakHold::new(hold_frames), hold_frames + 3);
+            assert!(response[..hold_frames].iter().all(|&x| x == 1.0), "{:?}", response);
+            assert_eq!(response[hold_frames], 0.0);
+        }
+
+        let mut detector = EnvelopeDetector::<[f32; 1], _>::peak_hold(3, 0.0, 10.0);
+        detector.next([1.0]);
+        for _ in 0..2 {
+            assert_eq!(detector.next([0.0]), [1.0]);
+        }
+        assert!(detector.next([0.0])[0] < 1.0);
+    }
+
+    #[test]
+    fn hold_release_holds_then_falls_back_in_db() {
+        let response = impulse_response(&mut HoldRelease::new(4, 1000.0, 1000.0), 6);
+        assert!(response[..4].iter().all(|&x| x == 1.0) && response[4] < 1.0, "{:?}", response);
+
+        let mut detector = EnvelopeDetector::new(HoldRelease::new(100, 20.0, 1000.0), 0.0, 0.0);
+        assert_eq!(detector.next([1.0f32]), [1.0]);
+        for _ in 0..99 {
+            assert_eq!(detector.next([0.0]), [1.0]);
+        }
+        let db = |x: f32| 20.0 * x.log10();
+        for _ in 0..1000 {
+            detector.next([0.0]);
+        }
+        assert!((db(detector.current()[0]) + 20.0).abs() < 0.01, "{:?}", detector.current());
+        for _ in 0..500 {
+            detector.next([0.0]);
+        }
+        assert!((db(detector.current()[0]) + 30.0).abs() < 0.01, "{:?}", detector.current());
+    }
+
+    #[test]
+    fn program_dependent_release_slows_for_sustained_material() {
+        use std::f32::consts::{E, PI};
+        let release_time = |n_frames: usize, sustained: bool| {
+            let mode = ProgramDependentRelease::<[f32; 1]>::new(100.0, 5000.0);
+            let mut detector = EnvelopeDetector::new(mode, 0.0, 0.0);
+            for i in 0..n_frames {
+                let x = if sustained { (i as f32 * 2.0 * PI / 48.0).sin() } else { 1.0 };
+                detector.next([x]);
+            }
+            let start = detector.current()[0];
+            let mut n = 0;
+            while detector.next([0.0])[0] > start / E {
+                n += 1;
+            }
+            n
+        };
+        let transient = release_time(20, false);
+        let sustained = release_time(20_000, true);
+        assert!((100..=125).contains(&transient), "{}", transient);
+        assert!((4900..=5100).contains(&sustained), "{}", sustained);
+
+        let mut mode = ProgramDependentRelease::<[f32; 1]>::new(100.0, 5000.0);
+        let fresh = mode;
+        for _ in 0..20_000 {
+            mode.next_frame([1.0]);
+        }
+        mode.reset();
+        assert_eq!(mode, fresh);
+    }
+
+    #[test]
+    fn program_dependent_release_bypasses_the_detector_release() {
+        let mode = ProgramDependentRelease::<[f32; 2]>::new(100.0, 5000.0);
+        let mut bypassed = EnvelopeDetector::new(mode, 0.0, 0.0);
+        let mut released = EnvelopeDetector::new(mode, 0.0, 1000.0);
+        released.set_release_frames_per_channel([500.0, 2000.0]);
+        released.set_release_slew_db_per_sec(1.0, 48_000.0);
+        for i in 0..200 {
+            let x = if i < 20 { [1.0, -0.5] } else { [0.0, 0.0] };
+            assert_eq!(released.next(x), bypassed.next(x));
+        }
+    }
+
+    #[test]
+    fn windowed_peak_is_the_sliding_maximum() {
+        let xs = [0.3f32, 0.9, 0.1, 0.5, 0.2, 0.7, 0.05, 0.4, 0.6, 0.0, 0.8];
+        let mut windowed = WindowedPeak::<[f32; 1]>::new(3);
+        for i in 0..xs.len() {
+            let start = i.saturating_sub(2);
+            let max = xs[start..i + 1].iter().cloned().fold(0.0, f32::max);
+            assert_eq!(windowed.next_frame([xs[i]]), [max]);
+        }
+
+        let mut windowed = WindowedPeak::<[f32; 2]>::new(5);
+        assert_eq!(windowed.next_frame([1.0, -0.5]), [1.0, 0.5]);
+        for _ in 1..5 {
+            assert_eq!(windowed.next_frame([0.0, 0.0]), [1.0, 0.5]);
+        }
+        assert_eq!(windowed.next_frame([0.0, 0.0]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn modes_may_be_switched_at_runtime() {
+        let mut detector: ::AnyEnvelopeDetector<[f32; 1]> =
+            EnvelopeDetector::new(AnyMode::from(Peak::full_wave()), 0.0, 0.0);
+        assert_eq!(detector.next([-0.5]), [0.5]);
+        *detector.mode_mut() = Rms::new(4).into();
+        assert_eq!(detector.next([-0.5]), [0.25]);
+        let mut boxed: Box<dyn Mode<[f32; 1]>> = Box::new(Rms::<[f32; 1]>::new(3));
+        assert_eq!(boxed.next_frame([1.0]), [(1.0f32 / 3.0).sqrt()]);
+    }
+
+    #[test]
+    fn retune_does_not_lose_small_ratios_or_drift() {
+        let mut peak_hold = PeakHold::<[f32; 1]>::new(100);
+        for _ in 0..10 {
+            Mode::<[f32; 1]>::retune(&mut peak_hold, 1.001);
+        }
+        assert_eq!(peak_hold.hold_frames(), 101);
+
+        let mut windowed = WindowedPeak::<[f32; 1]>::new(100);
+        let mut release = ProgramDependentRelease::<[f32; 1]>::new(100.0, 5000.0);
+        for _ in 0..1000 {
+            for &ratio in &[44_100.0 / 48_000.0, 48_000.0 / 44_100.0] {
+                Mode::<[f32; 1]>::retune(&mut windowed, ratio);
+                Mode::<[f32; 1]>::retune(&mut release, ratio);
+            }
+        }
+        assert_eq!(windowed.window_frames(), 100);
+        assert_eq!(release.fast_release_frames(), 100.0);
+        assert_eq!(release.slow_release_frames(), 5000.0);
+    }
 }
@@ -0,0 +1,216 @@
+//! Independent envelope detection over each frequency band of a signal.
+//!
+//! The primary type of interest in this module is the
+//! [**MultibandEnvelopeDetector**](./struct.MultibandEnvelopeDetector).
+
+use {EnvelopeDetector, FullScale, Mode};
+use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+use weighting::Biquad;
+
+
+/// Splits a signal into frequency bands via a chain of Linkwitz-Riley crossovers and detects the
+/// envelope of each band with its own **EnvelopeDetector**, i.e. for a multiband compressor.
+///
+/// Each crossover is a fourth-order Linkwitz-Riley (two cascaded second-order Butterworth) low-pass
+/// and high-pass pair. The lowest crossover splits the input, after which each higher crossover
+/// splits the high-pass output of the one before it, producing one more band than there are
+/// crossover frequencies. As only the envelopes are of interest, the bands are not all-pass
+/// compensated for summing back together.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>")))]
+pub struct MultibandEnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    /// The crossovers in ascending order of frequency.
+    crossovers: Vec<Crossover<F>>,
+    /// The detector for each band, from lowest to highest.
+    detectors: Vec<EnvelopeDetector<F, M>>,
+    /// The most recently detected envelope of each band.
+    envelopes: Vec<F>,
+}
+
+/// A fourth-order Linkwitz-Riley crossover.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+struct Crossover<F>
+    where F: Frame,
+{
+    low_pass: [Biquad<F>; 2],
+    high_pass: [Biquad<F>; 2],
+}
+
+impl<F, M> std::fmt::Debug for MultibandEnvelopeDetector<F, M>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "MultibandEnvelopeDetector {{ crossovers: {:?}, detectors: {:?}, \
+                   envelopes: {:?} }}",
+               &self.crossovers, &self.detectors, &self.envelopes)
+    }
+}
+
+impl<F> std::fmt::Debug for Crossover<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Crossover {{ low_pass: {:?}, high_pass: {:?} }}",
+               &self.low_pass, &self.high_pass)
+    }
+}
+
+impl<F, M> MultibandEnvelopeDetector<F, M>
+    where F: Frame,
+          M: Mode<F> + Clone,
+{
+
+    /// Construct a new **MultibandEnvelopeDetector** that splits a signal at the given sample
+    /// rate into bands at each of the given crossover frequencies.
+    ///
+    /// Each band is detected by a clone of the given `detector`. The detectors may be tuned
+    /// independently afterwards via `detectors_mut`.
+    ///
+    /// **Panics** if the crossover frequencies are not in ascending order.
+    pub fn new(detector: EnvelopeDetector<F, M>, crossover_hz: &[f64], sample_hz: f64) -> Self {
+        assert!(crossover_hz.windows(2).all(|pair| pair[0] < pair[1]),
+                "the crossover frequencies must be in ascending order");
+        let crossovers = crossover_hz.iter().map(|&hz| Crossover::new(hz, sample_hz)).collect();
+        let n_bands = crossover_hz.len() + 1;
+        MultibandEnvelopeDetector {
+            crossovers,
+            detectors: (0..n_bands).map(|_| detector.clone()).collect(),
+            envelopes: (0..n_bands).map(|_| F::equilibrium()).collect(),
+        }
+    }
+
+}
+
+impl<F, M> MultibandEnvelopeDetector<F, M>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+
+    /// The number of bands, one more than the number of crossovers.
+    pub fn n_bands(&self) -> usize {
+        self.detectors.len()
+    }
+
+    /// The detector for each band, from lowest to highest.
+    pub fn detectors(&self) -> &[EnvelopeDetector<F, M>] {
+        &self.detectors
+    }
+
+    /// Mutable access to the detector for each band, from lowest to highest.
+    pub fn detectors_mut(&mut self) -> &mut [EnvelopeDetector<F, M>] {
+        &mut self.detectors
+    }
+
+    /// Reset the crossover filters along with the detector for each band.
+    pub fn reset(&mut self) {
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for detector in &mut self.detectors {
+            detector.reset();
+        }
+        for envelope in &mut self.envelopes {
+            *envelope = F::equilibrium();
+        }
+    }
+
+    /// The most recently detected envelope of each band, from lowest to highest.
+    pub fn current(&self) -> &[F] {
+        &self.envelopes
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame of each band,
+    /// from lowest to highest.
+    pub fn next(&mut self, frame: F) -> &[F] {
+        let MultibandEnvelopeDetector { ref mut crossovers, ref mut detectors, ref mut envelopes } =
+            *self;
+        let mut rest = frame.to_float_frame();
+        for (i, crossover) in crossovers.iter_mut().enumerate() {
+            let (low, high) = crossover.next(rest);
+            envelopes[i] = detectors[i].next(low.map(|s| s.to_sample()));
+            rest = high;
+        }
+        let last = detectors.len() - 1;
+        envelopes[last] = detectors[last].next(rest.map(|s| s.to_sample()));
+        envelopes
+    }
+
+}
+
+impl<F> Crossover<F>
+    where F: Frame,
+{
+
+    fn new(hz: f64, sample_hz: f64) -> Self {
+        let low_pass = Biquad::butterworth_low_pass(hz, sample_hz);
+        let high_pass = Biquad::butterworth_high_pass(hz, sample_hz);
+        Crossover {
+            low_pass: [low_pass, low_pass],
+            high_pass: [high_pass, high_pass],
+        }
+    }
+
+    fn reset(&mut self) {
+        for stage in self.low_pass.iter_mut().chain(self.high_pass.iter_mut()) {
+            stage.reset();
+        }
+    }
+
+    /// Split the given frame into its `(low, high)` bands.
+    fn next(&mut self, frame: F::Float) -> (F::Float, F::Float) {
+        let low = self.low_pass.iter_mut().fold(frame, |x, stage| stage.next(x));
+        let high = self.high_pass.iter_mut().fold(frame, |x, stage| stage.next(x));
+        (low, high)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed a second of a sine at the given frequency into the detector.
+    fn feed_sine<M>(detector: &mut MultibandEnvelopeDetector<[f32; 1], M>, hz: f32)
+        where M: Mode<[f32; 1]>,
+    {
+        for i in 0..48_000 {
+            detector.next([(2.0 * std::f32::consts::PI * hz * i as f32 / 48_000.0).sin()]);
+        }
+    }
+
+    #[test]
+    fn each_band_detects_only_its_own_frequencies() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(10.0, 2000.0);
+        let mut multiband = MultibandEnvelopeDetector::new(detector, &[1000.0], 48_000.0);
+        assert_eq!(multiband.n_bands(), 2);
+        feed_sine(&mut multiband, 60.0);
+        assert!(multiband.current()[0][0] > 0.9, "{:?}", multiband.current());
+        assert!(multiband.current()[1][0] < 0.01, "{:?}", multiband.current());
+        multiband.reset();
+        feed_sine(&mut multiband, 8000.0);
+        assert!(multiband.current()[0][0] < 0.01, "{:?}", multiband.current());
+        assert!(multiband.current()[1][0] > 0.9, "{:?}", multiband.current());
+
+        let three = MultibandEnvelopeDetector::new(detector, &[200.0, 2000.0], 48_000.0);
+        assert_eq!(three.n_bands(), 3);
+    }
+}
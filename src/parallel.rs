@@ -0,0 +1,74 @@
+//! Parallel envelope detection across the channels of a signal.
+//!
+//! Only available when the `rayon` feature is enabled. The primary item of interest in this
+//! module is the [**process_channels_par**](./fn.process_channels_par) function.
+
+use {EnvelopeDetector, FullScale, Mode};
+use rayon::prelude::*;
+use sample::{Frame, Sample};
+
+
+/// Detect the envelope of each channel of `input` in parallel, writing the resulting envelope
+/// frames to `output`.
+///
+/// Each channel is processed by its own single channel **EnvelopeDetector**, where the detector
+/// at index `i` of `detectors` processes channel `i` of the signal. As each detector only holds
+/// its own state, the channels may be processed independently across threads. This is useful for
+/// offline analysis of signals with many channels (i.e. ambisonic files).
+///
+/// The results are the same as calling `next` on each detector for each frame in turn.
+///
+/// **Panics** if the number of `detectors` differs from the number of channels in `F`, or if the
+/// length of `input` differs from the length of `output`.
+pub fn process_channels_par<F, M>(detectors: &mut [EnvelopeDetector<[F::Sample; 1], M>],
+                                  input: &[F],
+                                  output: &mut [F])
+    where F: Frame + Sync,
+          F::Sample: FullScale + Send + Sync,
+          <F::Sample as Sample>::Float: Send,
+          M: Mode<[F::Sample; 1]> + Send,
+{
+    assert_eq!(detectors.len(), F::n_channels(),
+               "there must be exactly one detector for each channel");
+    assert_eq!(input.len(), output.len(),
+               "`input` and `output` slices must be the same length");
+
+    let channels: Vec<Vec<F::Sample>> = detectors.par_iter_mut()
+        .enumerate()
+        .map(|(i, detector)| {
+            input.iter()
+                .map(|frame| {
+                    let sample = *frame.channel(i).expect("one detector per channel");
+                    detector.next([sample])[0]
+                })
+                .collect()
+        })
+        .collect();
+
+    for (j, env_frame) in output.iter_mut().enumerate() {
+        *env_frame = F::from_fn(|i| channels[i][j]);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sequential_processing() {
+        let input: Vec<[f32; 3]> = (0..300)
+            .map(|i| [(i as f32 * 0.1).sin(), (i as f32 * 0.23).cos(), (i % 7) as f32 * 0.1])
+            .collect();
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(2.0, 30.0);
+        let mut detectors = vec![detector; 3];
+        let mut output = vec![[0.0; 3]; 300];
+        process_channels_par(&mut detectors, &input, &mut output);
+        let mut sequential = vec![detector; 3];
+        for (frame, env) in input.iter().zip(&output) {
+            for c in 0..3 {
+                assert_eq!(env[c], sequential[c].next([frame[c]])[0]);
+            }
+        }
+    }
+}
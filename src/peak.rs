@@ -1,9 +1,17 @@
 //! Peak envelope detection over a signal.
 //!
 //! The primary type of interest in this module is the [**Peak**](./struct.Peak) type, generic
-//! over any [**Rectifier**](./trait.Rectifier).
+//! over any [**Rectifier**](./trait.Rectifier) (including **MagnitudePeak** for complex-valued
+//! frames and **SignedPeak** for bipolar control signals). The [**DynPeak**](./struct.DynPeak)
+//! type allows for switching the rectification at runtime, while the
+//! [**TruePeak**](./struct.TruePeak) type additionally accounts for inter-sample peaks.
 
-use sample::{Frame, Sample};
+use FullScale;
+use sample::{FloatSample, Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 
 
@@ -16,6 +24,19 @@ pub enum NegativeHalfWave {}
 /// A rectifier that produces the absolute amplitude from samples from a signal.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum FullWave {}
+/// A rectifier that interprets a two channel frame as a complex `[re, im]` pair and produces its
+/// magnitude `sqrt(re^2 + im^2)` in both channels.
+///
+/// This produces a ripple-free envelope for analytic signals.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MagnitudePeak {}
+/// A rectifier that leaves each sample of the signal untouched, retaining its sign.
+///
+/// As the **EnvelopeDetector** chooses between its attack and release by comparing magnitudes,
+/// the resulting envelope tracks the excursion with the greatest magnitude while reporting it
+/// with its original sign, i.e. for shaping bipolar LFO and control signals.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SignedPeak {}
 
 
 /// Types that can rectify some incoming signal.
@@ -46,14 +67,32 @@ impl<F> Rectifier<F> for NegativeHalfWave
 
 impl<F> Rectifier<F> for FullWave
     where F: Frame,
+          F::Sample: FullScale,
 {
     #[inline]
     fn rectify(frame: F) -> F {
-        frame.map(|s| {
-            let signed = s.to_signed_sample();
-            if signed < Sample::equilibrium() { -signed } else { signed }
-                .to_sample()
-        })
+        frame.map(|s| ::magnitude(s).to_sample())
+    }
+}
+
+impl<F> Rectifier<F> for SignedPeak
+    where F: Frame,
+{
+    #[inline]
+    fn rectify(frame: F) -> F {
+        frame
+    }
+}
+
+impl<S> Rectifier<[S; 2]> for MagnitudePeak
+    where S: Sample,
+{
+    #[inline]
+    fn rectify(frame: [S; 2]) -> [S; 2] {
+        let re = frame[0].to_float_sample();
+        let im = frame[1].to_float_sample();
+        let magnitude = (re * re + im * im).sample_sqrt().to_sample();
+        [magnitude, magnitude]
     }
 }
 
@@ -63,6 +102,8 @@ impl<F> Rectifier<F> for FullWave
 ///
 /// It produces a peak-following envelope when rectify is called over a signal of samples.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
 pub struct Peak<R=FullWave> {
     rectifier: PhantomData<R>,
 }
@@ -94,6 +135,24 @@ impl Peak<NegativeHalfWave> {
     }
 }
 
+impl Peak<MagnitudePeak> {
+    /// A complex magnitude peak rectifier for two channel `[re, im]` frames.
+    pub fn magnitude() -> Peak<MagnitudePeak> {
+        Peak {
+            rectifier: PhantomData,
+        }
+    }
+}
+
+impl Peak<SignedPeak> {
+    /// A signed peak rectifier for bipolar signals.
+    pub fn signed() -> Peak<SignedPeak> {
+        Peak {
+            rectifier: PhantomData,
+        }
+    }
+}
+
 impl<R> Peak<R> {
     /// Return the rectified sample.
     #[inline]
@@ -104,3 +163,270 @@ impl<R> Peak<R> {
         R::rectify(frame)
     }
 }
+
+
+/// The rectification applied by a **DynPeak**, selectable at runtime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Rectification {
+    /// The absolute amplitude of the signal, as produced by the **FullWave** rectifier.
+    FullWave,
+    /// Only the positive samples of the signal, as produced by the **PositiveHalfWave** rectifier.
+    PositiveHalfWave,
+    /// Only the negative samples of the signal, as produced by the **NegativeHalfWave** rectifier.
+    NegativeHalfWave,
+}
+
+/// A peak rectifier whose **Rectification** may be switched at runtime (i.e. from a UI).
+///
+/// Where the rectification is known at compile time, prefer **Peak**.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DynPeak {
+    rectification: Rectification,
+}
+
+impl DynPeak {
+    /// Construct a new **DynPeak** with the given **Rectification**.
+    pub fn new(rectification: Rectification) -> Self {
+        DynPeak {
+            rectification,
+        }
+    }
+
+    /// The current **Rectification**.
+    pub fn rectification(&self) -> Rectification {
+        self.rectification
+    }
+
+    /// Switch to the given **Rectification**, taking effect from the next frame.
+    pub fn set_rectification(&mut self, rectification: Rectification) {
+        self.rectification = rectification;
+    }
+
+    /// Return the rectified frame.
+    #[inline]
+    pub fn rectify<F>(&self, frame: F) -> F
+        where F: Frame,
+              F::Sample: FullScale,
+    {
+        match self.rectification {
+            Rectification::FullWave => FullWave::rectify(frame),
+            Rectification::PositiveHalfWave => PositiveHalfWave::rectify(frame),
+            Rectification::NegativeHalfWave => NegativeHalfWave::rectify(frame),
+        }
+    }
+}
+
+impl From<Rectification> for DynPeak {
+    fn from(rectification: Rectification) -> Self {
+        DynPeak::new(rectification)
+    }
+}
+
+
+/// The number of input frames spanned by each phase of the **TruePeak** interpolation filter.
+pub const TRUE_PEAK_TAPS_PER_PHASE: usize = 12;
+
+/// A full wave peak rectifier that accounts for inter-sample peaks.
+///
+/// Each frame is upsampled by the oversampling factor using a windowed-sinc polyphase FIR filter
+/// before rectifying. The rectified value is the greatest magnitude across all of the upsampled
+/// frames, so peaks that occur between the samples of the signal (i.e. a full scale sine near
+/// Nyquist) are not missed as they would be by **Peak**. This is the approach used for true-peak
+/// metering by ITU-R BS.1770.
+///
+/// The interpolation filter delays the signal by roughly `TRUE_PEAK_TAPS_PER_PHASE / 2` frames.
+/// Note that the rectified value may exceed full scale, in which case integer sample formats
+/// will saturate.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub struct TruePeak<F>
+    where F: Frame,
+{
+    /// The most recent input frames, newest first.
+    history: VecDeque<F::Float>,
+    /// The interpolation filter coefficients, where the coefficient for tap `k` of phase `p` is
+    /// found at index `k * factor + p`.
+    coefficients: Vec<f32>,
+    /// The oversampling factor.
+    factor: usize,
+}
+
+impl<F> std::fmt::Debug for TruePeak<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "TruePeak {{ history: {:?}, coefficients: {:?}, factor: {:?} }}",
+               &self.history, &self.coefficients, &self.factor)
+    }
+}
+
+impl<F> TruePeak<F>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Construct a new **TruePeak** rectifier with the given oversampling factor.
+    ///
+    /// A factor of `4` is sufficient for most true-peak metering. A factor of `1` (or `0`)
+    /// performs no oversampling, in which case **TruePeak** behaves like **Peak**.
+    pub fn new(oversampling_factor: usize) -> Self {
+        let factor = if oversampling_factor > 1 { oversampling_factor } else { 1 };
+        TruePeak {
+            history: (0..TRUE_PEAK_TAPS_PER_PHASE).map(|_| Frame::equilibrium()).collect(),
+            coefficients: calc_interpolation_coefficients(factor),
+            factor,
+        }
+    }
+
+    /// The oversampling factor.
+    #[inline]
+    pub fn oversampling_factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Clear the history of the interpolation filter.
+    pub fn reset(&mut self) {
+        for frame in &mut self.history {
+            *frame = Frame::equilibrium();
+        }
+    }
+
+    /// Return the greatest rectified magnitude across all upsampled frames between the previous
+    /// frame and the given frame.
+    pub fn rectify(&mut self, frame: F) -> F {
+        if self.factor == 1 {
+            return FullWave::rectify(frame);
+        }
+
+        self.history.pop_back();
+        self.history.push_front(frame.to_float_frame());
+
+        let TruePeak { ref history, ref coefficients, factor } = *self;
+        let mut peak: F::Float = Frame::equilibrium();
+        for phase in 0..factor {
+            let upsampled = history.iter().enumerate()
+                .fold(Frame::equilibrium(), |y: F::Float, (k, &x)| {
+                    let coefficient = coefficients[k * factor + phase];
+                    y.add_amp(x.scale_amp(coefficient.to_sample()))
+                });
+            peak = peak.zip_map(upsampled, |p, s| {
+                let s = if s < Sample::equilibrium() { -s } else { s };
+                if s > p { s } else { p }
+            });
+        }
+        peak.map(|s| s.to_sample())
+    }
+
+}
+
+/// Windowed-sinc lowpass filter coefficients for interpolating by the given factor, with a cutoff
+/// at the Nyquist frequency of the original signal.
+fn calc_interpolation_coefficients(factor: usize) -> Vec<f32> {
+    const PI: f64 = std::f64::consts::PI;
+    let len = factor * TRUE_PEAK_TAPS_PER_PHASE;
+    let center = (len - 1) as f64 / 2.0;
+    (0..len)
+        .map(|n| {
+            let t = (n as f64 - center) / factor as f64;
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            // Blackman window.
+            let phase = 2.0 * PI * n as f64 / (len - 1) as f64;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            (sinc * window) as f32
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EnvelopeDetector;
+
+    #[test]
+    fn half_wave_rectifiers_pass_only_their_own_polarity() {
+        assert_eq!(Peak::<PositiveHalfWave>::rectify([0.5f32, -0.5]), [0.5, 0.0]);
+        assert_eq!(Peak::<NegativeHalfWave>::rectify([0.5f32, -0.5]), [0.0, -0.5]);
+        assert_eq!(Peak::<FullWave>::rectify([0.5f32, -0.5]), [0.5, 0.5]);
+        assert_eq!(<FullWave as Rectifier<[i16; 1]>>::rectify([-5]), [5]);
+    }
+
+    #[test]
+    fn negative_half_wave_envelope_attacks_away_from_equilibrium() {
+        let mut detector = EnvelopeDetector::new(Peak::negative_half_wave(), 10.0, 100.0);
+        let mut env = 0.0;
+        for _ in 0..10 {
+            let next = detector.next([-0.8f32])[0];
+            assert!(next < env && next > -0.8, "{} {}", env, next);
+            env = next;
+        }
+        for _ in 0..10 {
+            let next = detector.next([0.0f32])[0];
+            assert!(next > env && next < 0.0, "{} {}", env, next);
+            env = next;
+        }
+    }
+
+    #[test]
+    fn signed_peak_follows_the_polarity_of_the_largest_peak() {
+        let wave = |i: usize| match i % 100 {
+            p if p < 70 => 0.3f32,
+            p if p < 80 => -0.9,
+            _ => 0.0,
+        };
+        let mut detector = EnvelopeDetector::new(Peak::signed(), 0.0, 2000.0);
+        let mut inverted = EnvelopeDetector::new(Peak::signed(), 0.0, 2000.0);
+        for i in 0..1000 {
+            detector.next([wave(i)]);
+            inverted.next([-wave(i)]);
+        }
+        assert!(detector.current()[0] < -0.8, "{:?}", detector.current());
+        assert!(inverted.current()[0] > 0.8, "{:?}", inverted.current());
+    }
+
+    #[test]
+    fn magnitude_peak_tracks_a_rotating_analytic_signal() {
+        let mut detector = EnvelopeDetector::new(Peak::magnitude(), 0.0, 10.0);
+        for i in 0..500 {
+            let phase = i as f32 * 0.3;
+            let env = detector.next([0.7 * phase.cos(), 0.7 * phase.sin()]);
+            assert!((env[0] - 0.7).abs() < 1e-5 && env[0] == env[1], "{:?}", env);
+        }
+    }
+
+    #[test]
+    fn dyn_peak_switches_rectification_at_runtime() {
+        let mut detector = EnvelopeDetector::new(DynPeak::new(Rectification::FullWave), 0.0, 0.0);
+        assert_eq!(detector.next([-0.8f32]), [0.8]);
+        detector.mode_mut().set_rectification(Rectification::NegativeHalfWave);
+        assert_eq!(detector.next([-0.8f32]), [-0.8]);
+        detector.mode_mut().set_rectification(Rectification::PositiveHalfWave);
+        assert_eq!(detector.next([-0.8f32]), [0.0]);
+    }
+
+    #[test]
+    fn true_peak_finds_inter_sample_peaks() {
+        use std::f32::consts::PI;
+        let mut true_peak = TruePeak::<[f32; 1]>::new(4);
+        let (mut max_true, mut max_sample) = (0.0f32, 0.0f32);
+        // A sine at a quarter of the sample rate, sampled 45 degrees away from its peaks.
+        for i in 0..400 {
+            let x = [(PI * 0.5 * i as f32 + PI * 0.25).sin()];
+            max_true = max_true.max(true_peak.rectify(x)[0]);
+            max_sample = max_sample.max(x[0].abs());
+        }
+        assert!(max_sample < 0.71 && max_true > 0.95, "{} {}", max_sample, max_true);
+
+        let mut true_peak = TruePeak::<[f32; 1]>::new(4);
+        let mut level = 0.0;
+        for _ in 0..100 {
+            level = true_peak.rectify([0.5])[0];
+        }
+        assert!((level - 0.5).abs() < 0.01, "{}", level);
+    }
+}
@@ -1,15 +1,52 @@
 //! Root mean square calculation over a signal.
 //!
-//! The primary type of interest in this module is the [**Rms**](./struct.Rms).
+//! The primary type of interest in this module is the [**Rms**](./struct.Rms). An
+//! [**ExpRms**](./struct.ExpRms) is also provided for an exponentially weighted RMS that does
+//! not require a window buffer, along with an [**ArrayRms**](./type.ArrayRms) whose window is
+//! a fixed-length array. The [**WindowShape**](./enum.WindowShape) determines the weighting
+//! applied to each position of an **Rms** window.
 
 use sample::{FloatSample, Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std;
+use std::collections::VecDeque;
+
+
+/// The weighting applied to the frame square at each position of an **Rms** window when
+/// calculating the mean.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WindowShape {
+    /// Every frame square within the window is weighted equally.
+    ///
+    /// The mean is maintained via a running sum, costing O(1) per frame regardless of the window
+    /// length. The RMS jumps as loud frames enter and leave the window.
+    ///
+    /// This is the default.
+    #[default]
+    Rectangular,
+    /// Frame squares are weighted by a Hann window, so that frames fade in as they enter the
+    /// window and fade out as they leave it, producing a smoother RMS.
+    ///
+    /// The weighted mean is recalculated from every position in the window for each frame,
+    /// costing O(n) per frame for a window of `n` frames.
+    Hann,
+}
 
 
 /// Iteratively extracts the RMS (root mean square) envelope from a window over a signal of
 /// sample `Frame`s.
+///
+/// The window of frame squares is stored in the **RingBuffer** `W`. By default this is a
+/// `VecDeque` owned by the **Rms**, so that it may be reset or re-sized without any bounds beyond
+/// `F: Frame`. For a window that requires no allocation, see **ArrayRms**.
 #[derive(Clone)]
-pub struct Rms<F>
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize, W: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>, \
+                                                         W: Deserialize<'de>")))]
+pub struct Rms<F, W = VecDeque<<F as Frame>::Float>>
     where F: Frame,
 {
     /// The type of `Frame`s for which the RMS will be calculated.
@@ -19,18 +56,58 @@ pub struct Rms<F>
     ///
     /// When a new sample is received, the **Rms** pops the front sample_square and adds the new
     /// sample_square to the back.
-    window: std::collections::VecDeque<F::Float>,
+    window: W,
+    /// The length of the `window` in frames before rounding, from which `retune` rescales so
+    /// that repeated calls do not accumulate rounding error.
+    exact_window_frames: f64,
     /// The sum total of all sample_squares currently within the **Rms**'s `window` ring buffer.
     sum: F::Float,
+    /// The number of frames pushed since the `sum` was last recomputed from the `window`.
+    ///
+    /// The running `sum` accumulates floating point error from the repeated addition and
+    /// subtraction of frame squares, so it is recomputed each time the `window` has been fully
+    /// replaced. This bounds the error without drift at an amortised cost of one addition per
+    /// frame.
+    frames_since_recalc: usize,
+    /// Per-channel sums at or below this value are snapped to exactly zero as frames are removed
+    /// from the `window`.
+    sum_epsilon: f32,
+    /// The weighting applied to each position of the `window`.
+    shape: WindowShape,
+    /// The weight for each position of the `window` (oldest first) normalised to sum to `1.0`,
+    /// or empty for a **Rectangular** window.
+    weights: Vec<f32>,
 }
 
-impl<F> std::fmt::Debug for Rms<F>
+impl<F, W> std::fmt::Debug for Rms<F, W>
     where F: Frame,
           F::Float: std::fmt::Debug,
+          W: std::fmt::Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "Rms {{ frame: {:?}, window: {:?}, sum: {:?} }}",
-               &self.frame, &self.window, &self.sum)
+        write!(f, "Rms {{ frame: {:?}, window: {:?}, exact_window_frames: {:?}, sum: {:?}, \
+                   frames_since_recalc: {:?}, sum_epsilon: {:?}, shape: {:?} }}",
+               &self.frame, &self.window, &self.exact_window_frames, &self.sum,
+               &self.frames_since_recalc, &self.sum_epsilon, &self.shape)
+    }
+}
+
+impl<F, W> PartialEq for Rms<F, W>
+    where F: Frame,
+          F::Float: PartialEq,
+          W: RingBuffer<F::Float>,
+{
+    /// Two **Rms**s are equal if the contents of their `window`s (from oldest to newest), their
+    /// `sum`s, the number of
+    /// frames since their `sum`s were recomputed, their `sum_epsilon`s and their
+    /// **WindowShape**s are equal.
+    ///
+    /// The recompute count is compared as two otherwise equal **Rms**s that recompute their
+    /// `sum`s on different frames may go on to produce slightly different results.
+    fn eq(&self, other: &Self) -> bool {
+        self.squares().eq(other.squares()) && self.sum == other.sum
+            && self.frames_since_recalc == other.frames_since_recalc
+            && self.sum_epsilon == other.sum_epsilon && self.shape == other.shape
     }
 }
 
@@ -41,19 +118,85 @@ impl<F> Rms<F>
 
     /// Construct a new **Rms**.
     pub fn new(n_window_frames: usize) -> Self {
+        Self::with_sum_epsilon(n_window_frames, 0.0)
+    }
+
+    /// Construct a new **Rms** whose running `sum` snaps to exactly zero on any channel where
+    /// it falls to or below `sum_epsilon` as frames leave the `window`.
+    ///
+    /// The running `sum` is only ever clamped at equilibrium by `new`. For very quiet signals the
+    /// rounding error accumulated from the addition and subtraction of frame squares may leave a
+    /// small positive residue in the `sum`, which shows as a noise floor in the RMS during
+    /// silence. Snapping the `sum` to zero below some small epsilon (i.e. `1e-9`) ensures that
+    /// the RMS returns to exactly equilibrium once a window of digital silence has been received.
+    ///
+    /// The `sum_epsilon` is in units of summed frame squares. Negative and NaN values are treated
+    /// as `0.0`.
+    pub fn with_sum_epsilon(n_window_frames: usize, sum_epsilon: f32) -> Self {
         Rms {
             frame: std::marker::PhantomData,
             window: (0..n_window_frames).map(|_| Frame::equilibrium()).collect(),
+            exact_window_frames: n_window_frames as f64,
             sum: Frame::equilibrium(),
+            frames_since_recalc: 0,
+            sum_epsilon: if sum_epsilon > 0.0 { sum_epsilon } else { 0.0 },
+            shape: WindowShape::Rectangular,
+            weights: Vec::new(),
         }
     }
 
-    /// Zeroes the sum and the buffer of the `window`.
-    pub fn reset(&mut self) {
-        for sample_square in &mut self.window {
-            *sample_square = Frame::equilibrium();
+    /// Construct a new **Rms** whose frame squares are weighted by the given **WindowShape**.
+    pub fn with_shape(n_window_frames: usize, shape: WindowShape) -> Self {
+        let mut rms = Self::new(n_window_frames);
+        rms.set_shape(shape);
+        rms
+    }
+
+    /// Construct a new **ArrayRms**, whose window of `N` frames is stored inline as an array
+    /// rather than in a `VecDeque`, i.e. `Rms::<[f32; 2]>::with_array::<128>()`.
+    ///
+    /// The **ArrayRms** produces the same RMS as `Rms::new(N)`, but requires no allocation. Its
+    /// `sum_epsilon` and **WindowShape** may be given via `set_sum_epsilon` and `set_shape`.
+    pub fn with_array<const N: usize>() -> ArrayRms<F, N> {
+        Rms {
+            frame: std::marker::PhantomData,
+            window: ArrayRing::new(Frame::equilibrium()),
+            exact_window_frames: N as f64,
+            sum: Frame::equilibrium(),
+            frames_since_recalc: 0,
+            sum_epsilon: 0.0,
+            shape: WindowShape::Rectangular,
+            weights: Vec::new(),
         }
-        self.sum = Frame::equilibrium();
+    }
+
+    /// Construct an **Rms** from a `window` of frame squares (i.e. `sample * sample`, oldest
+    /// first) and the `sum` of those frame squares.
+    ///
+    /// This is the inverse of `into_parts`, and is useful for restoring some previously saved
+    /// state or for sharing a window across detectors.
+    ///
+    /// The caller is responsible for `sum` being consistent with the contents of `window`. An
+    /// inconsistent `sum` will produce an incorrect RMS until the `sum` is next recomputed from
+    /// the `window` (at the latest once the `window` has been fully replaced).
+    pub fn from_parts(window: VecDeque<F::Float>, sum: F::Float) -> Self {
+        Rms {
+            frame: std::marker::PhantomData,
+            exact_window_frames: window.len() as f64,
+            window,
+            sum,
+            frames_since_recalc: 0,
+            sum_epsilon: 0.0,
+            shape: WindowShape::Rectangular,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Consume the **Rms** and return its `window` of frame squares (oldest first) along with
+    /// their `sum`.
+    pub fn into_parts(self) -> (VecDeque<F::Float>, F::Float) {
+        let Rms { window, sum, .. } = self;
+        (window, sum)
     }
 
     /// Set the size of the `window` as a number of frames.
@@ -65,7 +208,26 @@ impl<F> Rms<F>
     /// pushed to the front of the `window` using frames at signal equilibrium.
     ///
     /// If the length already is already correct, no re-sizing occurs.
+    ///
+    /// Whenever the window is re-sized, the `sum` is recomputed from the retained contents of the
+    /// `window` so that the RMS is immediately correct for the new window length.
     pub fn set_window_frames(&mut self, n_window_frames: usize) {
+        self.exact_window_frames = n_window_frames as f64;
+        self.resize_window(n_window_frames);
+    }
+
+    /// Rescale the length of the `window` following a change in sample rate, where `ratio` is the
+    /// new sample rate divided by the old.
+    ///
+    /// The length is rescaled from its exact value before rounding to the nearest frame, so that
+    /// repeated calls do not accumulate rounding error.
+    pub(crate) fn retune(&mut self, ratio: f64) {
+        self.exact_window_frames = ::retune_frames(self.exact_window_frames, ratio);
+        self.resize_window(self.exact_window_frames.round() as usize);
+    }
+
+    /// Re-size the `window` to the given number of frames, as described by `set_window_frames`.
+    fn resize_window(&mut self, n_window_frames: usize) {
         let len = self.window.len();
         if len == n_window_frames {
             return;
@@ -74,7 +236,7 @@ impl<F> Rms<F>
         } else if len > n_window_frames {
             let diff = len - n_window_frames;
             for _ in 0..diff {
-                self.pop_front();
+                self.window.pop_front();
             }
 
         // If our window is too short, we'll zero-pad the front of the ringbuffer (this way, the
@@ -85,6 +247,55 @@ impl<F> Rms<F>
                 self.window.push_front(Frame::equilibrium());
             }
         }
+
+        self.sum = self.calc_sum();
+        self.frames_since_recalc = 0;
+        self.weights = calc_weights(self.shape, n_window_frames);
+    }
+
+}
+
+impl<F, W> Rms<F, W>
+    where F: Frame,
+          W: RingBuffer<F::Float>,
+{
+
+    /// Zeroes the sum and the buffer of the `window`.
+    ///
+    /// The length of the `window` and the **WindowShape** are retained, and no reallocation
+    /// occurs.
+    pub fn reset(&mut self) {
+        self.window.fill(Frame::equilibrium());
+        self.sum = Frame::equilibrium();
+        self.frames_since_recalc = 0;
+    }
+
+    /// The **WindowShape** used to weight each position of the `window`.
+    pub fn shape(&self) -> WindowShape {
+        self.shape
+    }
+
+    /// Set the **WindowShape** used to weight each position of the `window`.
+    ///
+    /// This takes effect immediately, applying the new weighting to the frame squares already
+    /// within the `window`.
+    pub fn set_shape(&mut self, shape: WindowShape) {
+        self.shape = shape;
+        self.weights = calc_weights(shape, self.window.len());
+    }
+
+    /// The value at or below which the running `sum` snaps to zero.
+    ///
+    /// See `with_sum_epsilon`.
+    pub fn sum_epsilon(&self) -> f32 {
+        self.sum_epsilon
+    }
+
+    /// Set the value at or below which the running `sum` snaps to zero.
+    ///
+    /// See `with_sum_epsilon`. Negative and NaN values are treated as `0.0`.
+    pub fn set_sum_epsilon(&mut self, sum_epsilon: f32) {
+        self.sum_epsilon = if sum_epsilon > 0.0 { sum_epsilon } else { 0.0 };
     }
 
     /// The length of the window as a number of frames.
@@ -93,6 +304,15 @@ impl<F> Rms<F>
         self.window.len()
     }
 
+    /// An iterator yielding the frame squares (i.e. `sample * sample`) currently within the
+    /// `window`, from oldest to newest.
+    ///
+    /// The iterator yields exactly `window_frames` frame squares, the last of which is the square
+    /// of the most recently received frame.
+    pub fn squares<'a>(&'a self) -> impl Iterator<Item=&'a F::Float> + 'a {
+        (0..self.window.len()).map(move |index| self.window.get(index))
+    }
+
     /// The next RMS given the new frame in the sequence.
     ///
     /// The **Rms** pops its front frame and adds the new frame to the back.
@@ -104,38 +324,699 @@ impl<F> Rms<F>
     #[inline]
     pub fn next(&mut self, new_frame: F) -> F::Float {
         // If our **Window** has no length, there's nothing to calculate.
-        if self.window.len() == 0 {
+        if self.window.is_empty() {
             return Frame::equilibrium();
         }
-        self.pop_front();
-        self.push_back(new_frame.to_float_frame());
+        self.push(new_frame.to_float_frame());
         self.calc_rms()
     }
 
-    /// Remove the front frame and subtract it from the `sum` frame.
-    fn pop_front(&mut self) {
-        let removed_sample_square = self.window.pop_front().unwrap();
+    /// The next mean square given the new frame in the sequence.
+    ///
+    /// This updates the `window` in the same manner as `next`, but yields the mean of all frame
+    /// squares in the `window` without taking the square root. That is, the value returned by
+    /// `next` is the square root of the value that would be returned by `next_mean_square`.
+    ///
+    /// Returns `Frame::equilibrium` if the `window` is empty.
+    #[inline]
+    pub fn next_mean_square(&mut self, new_frame: F) -> F::Float {
+        if self.window.is_empty() {
+            return Frame::equilibrium();
+        }
+        self.push(new_frame.to_float_frame());
+        self.mean_square()
+    }
+
+    /// The next windowed power (mean square) given the new frame in the sequence.
+    ///
+    /// This is the same as `next_mean_square`, and is intended for combining the energy of
+    /// several sources (i.e. the inputs of a summing bus). Summing RMS values overstates the
+    /// combined level, whereas the powers of each source may be summed directly, after which a
+    /// single square root yields the combined RMS. For a single source, the square root of
+    /// `next_power` is equal to the RMS yielded by `next`.
+    ///
+    /// Returns `Frame::equilibrium` if the `window` is empty.
+    #[inline]
+    pub fn next_power(&mut self, new_frame: F) -> F::Float {
+        self.next_mean_square(new_frame)
+    }
+
+    /// The RMS after pushing a whole hop of frames onto the `window`, i.e. for block processing
+    /// where the RMS is only sampled at hop boundaries.
+    ///
+    /// Each frame of the `block` updates the running `sum` exactly as it would via `next`, so the
+    /// yielded RMS is equal to that which `next` would yield for the final frame of the `block`.
+    /// The RMS itself is only calculated once, after the whole `block` has been pushed. Where the
+    /// hop is shorter than the `window`, consecutive hops overlap.
+    ///
+    /// Returns the RMS of the current state if the `block` is empty, or `Frame::equilibrium` if
+    /// the `window` is empty.
+    pub fn next_hop(&mut self, block: &[F]) -> F::Float {
+        if self.window.is_empty() {
+            return Frame::equilibrium();
+        }
+        for &new_frame in block {
+            self.push(new_frame.to_float_frame());
+        }
+        self.calc_rms()
+    }
+
+    /// The mean of all frame squares currently within the `window`.
+    ///
+    /// This is the RMS without the square root, and is useful for comparing levels (i.e. against
+    /// a squared threshold) without the cost of the square root. The RMS of the current state is
+    /// equal to the square root of the mean square.
+    ///
+    /// For **WindowShape**s other than **Rectangular** this is the weighted mean.
+    ///
+    /// Returns `Frame::equilibrium` if the `window` is empty.
+    pub fn mean_square(&self) -> F::Float {
+        // An empty window has no frames to average over.
+        if self.window.is_empty() {
+            return Frame::equilibrium();
+        }
+        if self.shape != WindowShape::Rectangular {
+            return self.squares().zip(&self.weights)
+                .fold(Frame::equilibrium(), |mean: F::Float, (&sample_square, &weight)| {
+                    mean.add_amp(sample_square.scale_amp(weight.to_sample()))
+                });
+        }
+        let num_frames_f = Sample::from_sample(self.window.len() as f32);
+        self.sum.map(|s| s / num_frames_f)
+    }
+
+    /// Fill the `window` as though a steady signal with the given RMS had been received.
+    pub(crate) fn seed(&mut self, rms: F::Float) {
+        self.window.fill(rms.zip_map(rms, |a, b| a * b));
+        self.sum = self.calc_sum();
+        self.frames_since_recalc = 0;
+    }
+
+    /// Replace the oldest frame square within the `window` with the square of the new frame,
+    /// subtracting the former from and adding the latter to the `sum`.
+    fn push(&mut self, new_frame: F::Float) {
+        let new_frame_square = new_frame.zip_map(new_frame, |a, b| a * b);
+        let removed_sample_square = self.window.push_pop(new_frame_square);
+        let epsilon: <F::Sample as Sample>::Float = self.sum_epsilon.to_sample();
         self.sum = self.sum.zip_map(removed_sample_square, |s, r| {
             let diff = s - r;
-            // Don't let floating point rounding errors put us below 0.0.
-            if diff < Sample::equilibrium() { Sample::equilibrium() } else { diff }
+            // Don't let floating point rounding errors put us below 0.0 (or leave a residue at or
+            // below the epsilon).
+            if diff <= epsilon { Sample::equilibrium() } else { diff }
         });
+        // Add the new frame square to the `sum`, periodically recomputing the `sum` from scratch
+        // to discard any accumulated rounding error.
+        self.frames_since_recalc += 1;
+        if self.frames_since_recalc >= self.window.len() {
+            self.sum = self.calc_sum();
+            self.frames_since_recalc = 0;
+        } else {
+            self.sum = self.sum.add_amp(new_frame_square);
+        }
     }
 
-    /// Determines the square of the given frame, pushes it back onto our buffer and adds it to
-    /// the `sum`.
-    fn push_back(&mut self, new_frame: F::Float) {
-        // Push back the new frame_square and add it to the `sum`.
-        let new_frame_square = new_frame.zip_map(new_frame, |a, b| a * b);
-        self.window.push_back(new_frame_square);
-        self.sum = self.sum.add_amp(new_frame_square);
+    /// Sum all frame squares currently within the `window`.
+    fn calc_sum(&self) -> F::Float {
+        self.squares().fold(Frame::equilibrium(), |sum: F::Float, &sample_square| {
+            sum.add_amp(sample_square)
+        })
     }
 
     /// Calculate the RMS for the **Window** in its current state and yield the result as the
     /// `Frame`s associated `Float` type.
     fn calc_rms(&self) -> F::Float {
-        let num_frames_f = Sample::from_sample(self.window.len() as f32);
-        self.sum.map(|s| (s / num_frames_f).sample_sqrt())
+        self.mean_square().map(|s| s.sample_sqrt())
+    }
+
+}
+
+#[cfg(feature = "simd")]
+impl<F, W> Rms<F, W>
+    where F: Frame,
+          F::Float: AsRef<[f32]> + AsMut<[f32]>,
+          W: RingBuffer<F::Float>,
+{
+
+    /// The next RMS given the new frame in the sequence, squaring the frame and updating the
+    /// running `sum` with SIMD instructions across the channel dimension.
+    ///
+    /// This is only available when the `simd` feature is enabled, and only for frames of `f32`
+    /// samples. The result is the same as `next` to within floating point tolerance, though it
+    /// is significantly faster for frames with many channels. Targets without SIMD support fall
+    /// back to scalar instructions.
+    ///
+    /// Returns `Frame::equilibrium` if the `window` is empty.
+    pub fn next_simd(&mut self, new_frame: F) -> F::Float {
+        if self.window.is_empty() {
+            return Frame::equilibrium();
+        }
+        let mut new_frame_square = new_frame.to_float_frame();
+        simd::square(new_frame_square.as_mut());
+        let removed_frame_square = self.window.push_pop(new_frame_square);
+        self.frames_since_recalc += 1;
+        if self.frames_since_recalc >= self.window.len() {
+            self.sum = self.calc_sum();
+            self.frames_since_recalc = 0;
+        } else {
+            simd::update_sum(self.sum.as_mut(),
+                             removed_frame_square.as_ref(),
+                             new_frame_square.as_ref(),
+                             self.sum_epsilon);
+        }
+        self.calc_rms()
+    }
+
+}
+
+/// SIMD kernels for the **Rms** hot path, processing eight channels at a time with a scalar
+/// remainder.
+#[cfg(feature = "simd")]
+mod simd {
+    use wide::f32x8;
+
+    const LANES: usize = 8;
+
+    fn load(chunk: &[f32]) -> f32x8 {
+        let mut array = [0.0; LANES];
+        array.copy_from_slice(chunk);
+        f32x8::new(array)
+    }
+
+    /// Square each sample in place.
+    pub fn square(samples: &mut [f32]) {
+        let mut chunks = samples.chunks_exact_mut(LANES);
+        for chunk in &mut chunks {
+            let v = load(chunk);
+            chunk.copy_from_slice(&(v * v).to_array());
+        }
+        for s in chunks.into_remainder() {
+            *s = *s * *s;
+        }
+    }
+
+    /// Subtract the removed squares from the sum (snapping to `0.0` at or below the `epsilon` to
+    /// guard against rounding error) and add the new squares.
+    pub fn update_sum(sum: &mut [f32], removed: &[f32], added: &[f32], epsilon: f32) {
+        let mut sum_chunks = sum.chunks_exact_mut(LANES);
+        let mut removed_chunks = removed.chunks_exact(LANES);
+        let mut added_chunks = added.chunks_exact(LANES);
+        let zero = f32x8::new([0.0; LANES]);
+        for ((s, r), a) in (&mut sum_chunks).zip(&mut removed_chunks).zip(&mut added_chunks) {
+            let mut diff = (load(s) - load(r)).max(zero);
+            if epsilon > 0.0 {
+                let mut array = diff.to_array();
+                for d in &mut array {
+                    if *d <= epsilon { *d = 0.0; }
+                }
+                diff = f32x8::new(array);
+            }
+            s.copy_from_slice(&(diff + load(a)).to_array());
+        }
+        let remainder = sum_chunks.into_remainder().iter_mut()
+            .zip(removed_chunks.remainder())
+            .zip(added_chunks.remainder());
+        for ((s, &r), &a) in remainder {
+            let diff = *s - r;
+            let diff = if diff <= epsilon { 0.0 } else { diff };
+            *s = diff + a;
+        }
+    }
+}
+
+
+/// The ring buffer of frame squares backing the `window` of an **Rms**.
+///
+/// This is implemented for `VecDeque`, which the **Rms** may re-size at runtime, and for
+/// **ArrayRing**, which stores a fixed number of frame squares inline.
+pub trait RingBuffer<S> {
+    /// The number of frame squares within the buffer.
+    fn len(&self) -> usize;
+    /// Whether the buffer holds no frame squares.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The frame square at the given index, where `0` is the oldest.
+    fn get(&self, index: usize) -> &S;
+    /// Replace the oldest frame square with the given frame square, which becomes the newest,
+    /// and return the frame square that was replaced.
+    ///
+    /// **Panics** if the buffer is empty.
+    fn push_pop(&mut self, square: S) -> S;
+    /// Assign the given frame square to every position within the buffer.
+    fn fill(&mut self, square: S);
+}
+
+impl<S> RingBuffer<S> for VecDeque<S>
+    where S: Copy,
+{
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+    fn get(&self, index: usize) -> &S {
+        &self[index]
+    }
+    fn push_pop(&mut self, square: S) -> S {
+        let removed = self.pop_front().expect("the ring buffer is empty");
+        self.push_back(square);
+        removed
+    }
+    fn fill(&mut self, square: S) {
+        for s in self.iter_mut() {
+            *s = square;
+        }
+    }
+}
+
+/// A ring buffer of `N` frame squares stored inline as an array, backing the window of an
+/// **ArrayRms**.
+#[derive(Copy, Clone, Debug)]
+pub struct ArrayRing<S, const N: usize> {
+    /// The frame squares, where the oldest is found at `head`.
+    squares: [S; N],
+    /// The index of the oldest frame square within `squares`.
+    head: usize,
+}
+
+impl<S, const N: usize> ArrayRing<S, N>
+    where S: Copy,
+{
+    /// Construct an **ArrayRing** with every position set to the given frame square.
+    fn new(square: S) -> Self {
+        ArrayRing {
+            squares: [square; N],
+            head: 0,
+        }
+    }
+}
+
+impl<S, const N: usize> RingBuffer<S> for ArrayRing<S, N>
+    where S: Copy,
+{
+    fn len(&self) -> usize {
+        N
+    }
+    fn get(&self, index: usize) -> &S {
+        &self.squares[(self.head + index) % N]
+    }
+    fn push_pop(&mut self, square: S) -> S {
+        let removed = std::mem::replace(&mut self.squares[self.head], square);
+        self.head = (self.head + 1) % N;
+        removed
+    }
+    fn fill(&mut self, square: S) {
+        self.squares = [square; N];
+    }
+}
+
+/// An **Rms** whose window of `N` frames is stored inline as an array rather than on the heap.
+///
+/// This produces the same RMS as an **Rms** with a window of `N` frames and supports the same
+/// `sum_epsilon` and **WindowShape**, but requires no allocation (besides the weights of a
+/// **WindowShape** other than **Rectangular**, which are calculated once by `set_shape`), making
+/// it suitable for real-time use. As the window length is fixed by the type, it cannot be
+/// re-sized at runtime.
+///
+/// Construct an **ArrayRms** via **Rms::with_array**. Note that `serde` only supports arrays of
+/// up to 32 elements, so the **ArrayRms** is not serializable.
+pub type ArrayRms<F, const N: usize> = Rms<F, ArrayRing<<F as Frame>::Float, N>>;
+
+impl<F, const N: usize> Default for ArrayRms<F, N>
+    where F: Frame,
+{
+    fn default() -> Self {
+        Rms::with_array()
+    }
+}
+
+
+/// Iteratively extracts an exponentially weighted RMS envelope from a signal of sample `Frame`s.
+///
+/// Rather than storing a window of frame squares, the **ExpRms** maintains a single-pole running
+/// mean square, updated per frame as `ms = ms + alpha * (frame_square - ms)`. This requires no
+/// allocation regardless of the window length.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub struct ExpRms<F>
+    where F: Frame,
+{
+    /// The running mean square.
+    mean_square: F::Float,
+    /// The averaging time constant in frames, kept at `f64` precision so that `retune` may
+    /// rescale it repeatedly without accumulating rounding error.
+    window_frames: f64,
+    /// The averaging coefficient applied to each new frame square.
+    alpha: f32,
+}
+
+impl<F> ExpRms<F>
+    where F: Frame,
+{
+
+    /// Construct a new **ExpRms** whose averaging time constant is the given number of frames.
+    pub fn new(n_window_frames: f32) -> Self {
+        ExpRms {
+            mean_square: Frame::equilibrium(),
+            window_frames: n_window_frames as f64,
+            alpha: calc_alpha(n_window_frames),
+        }
+    }
+
+    /// Zeroes the running mean square.
+    pub fn reset(&mut self) {
+        self.mean_square = Frame::equilibrium();
+    }
+
+    /// Set the averaging time constant as a number of frames.
+    pub fn set_window_frames(&mut self, n_window_frames: f32) {
+        self.window_frames = n_window_frames as f64;
+        self.alpha = calc_alpha(n_window_frames);
+    }
+
+    /// Rescale the averaging time constant following a change in sample rate, where `ratio` is
+    /// the new sample rate divided by the old.
+    pub(crate) fn retune(&mut self, ratio: f64) {
+        self.window_frames = ::retune_frames(self.window_frames, ratio);
+        self.alpha = calc_alpha(self.window_frames as f32);
+    }
+
+    /// The averaging time constant as a number of frames.
+    pub fn window_frames(&self) -> f32 {
+        ::frames_from_gain(1.0 - self.alpha)
+    }
+
+    /// The averaging coefficient applied to each new frame square.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Set the running mean square as though a steady signal with the given RMS had been
+    /// received.
+    pub(crate) fn seed(&mut self, rms: F::Float) {
+        self.mean_square = rms.zip_map(rms, |a, b| a * b);
+    }
+
+    /// The next RMS given the new frame in the sequence.
+    #[inline]
+    pub fn next(&mut self, new_frame: F) -> F::Float {
+        let alpha: <F::Sample as Sample>::Float = self.alpha.to_sample();
+        let new_frame = new_frame.to_float_frame();
+        self.mean_square = self.mean_square.zip_map(new_frame, |ms, s| ms + (s * s - ms) * alpha);
+        self.mean_square.map(|ms| ms.sample_sqrt())
+    }
+
+}
+
+/// The weight for each of the `n_window_frames` positions of a window of the given shape,
+/// normalised to sum to `1.0`.
+///
+/// Returns an empty `Vec` for a **Rectangular** window, whose mean is found via the running sum.
+fn calc_weights(shape: WindowShape, n_window_frames: usize) -> Vec<f32> {
+    match shape {
+        WindowShape::Rectangular => Vec::new(),
+        WindowShape::Hann => {
+            // The zero valued end points are excluded, so every frame in the window contributes.
+            let len = n_window_frames as f64 + 1.0;
+            let weights: Vec<f64> = (1..n_window_frames + 1)
+                .map(|n| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * n as f64 / len).cos())
+                .collect();
+            let total: f64 = weights.iter().sum();
+            weights.iter().map(|&w| (w / total) as f32).collect()
+        },
+    }
+}
+
+/// The averaging coefficient for a single-pole mean with the given time constant in frames.
+fn calc_alpha(n_window_frames: f32) -> f32 {
+    1.0 - ::gain_from_frames(n_window_frames)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_window_produces_equilibrium() {
+        let mut rms = Rms::<[f32; 1]>::new(0);
+        assert_eq!(rms.next([1.0]), [0.0]);
+        assert_eq!(rms.mean_square(), [0.0]);
+        assert_eq!(rms.next_hop(&[[1.0]]), [0.0]);
+        assert_eq!(Rms::<[f32; 1]>::with_array::<0>().next([1.0]), [0.0]);
+    }
+
+    #[test]
+    fn rms_of_a_constant_signal_is_its_magnitude() {
+        let mut rms = Rms::<[f32; 2]>::new(4);
+        let mut result = [0.0; 2];
+        for _ in 0..4 {
+            result = rms.next([0.5, -0.25]);
+        }
+        assert_eq!(result, [0.5, 0.25]);
+    }
+
+    #[test]
+    fn mean_square_is_the_square_of_the_rms() {
+        let mut rms = Rms::<[f32; 1]>::new(4);
+        for i in 0..9 {
+            let result = rms.next([i as f32 * 0.1]);
+            assert!((rms.mean_square()[0].sqrt() - result[0]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn set_window_frames_keeps_the_sum_consistent() {
+        let signal: Vec<f32> = (0..40).map(|i| ((i * 7) % 11) as f32 / 11.0).collect();
+        let mut rms = Rms::<[f32; 1]>::new(8);
+        for &x in &signal[..30] {
+            rms.next([x]);
+        }
+
+        // Shrinking retains the newest frames.
+        rms.set_window_frames(4);
+        let mut shorter = Rms::<[f32; 1]>::new(4);
+        for &x in &signal[26..30] {
+            shorter.next([x]);
+        }
+        for &x in &signal[30..] {
+            assert!((rms.next([x])[0] - shorter.next([x])[0]).abs() < 1e-6);
+        }
+
+        // Growing pads the oldest frames with equilibrium.
+        rms.set_window_frames(12);
+        let mut longer = Rms::<[f32; 1]>::new(12);
+        for &x in &signal[36..] {
+            longer.next([x]);
+        }
+        assert!((rms.next([0.3])[0] - longer.next([0.3])[0]).abs() < 1e-6);
     }
 
+    #[test]
+    fn running_sum_does_not_drift_over_long_signals() {
+        let mut rms = Rms::<[f32; 1]>::new(999);
+        let mut result = [0.0];
+        for i in 0..1_000_000u32 {
+            let x = match i % 3 {
+                0 => 0.9,
+                1 => -0.9,
+                _ => 0.1,
+            };
+            result = rms.next([x]);
+        }
+        let expected = ((0.81f32 * 2.0 + 0.01) / 3.0).sqrt();
+        assert!((result[0] - expected).abs() < 1e-4, "{} {}", result[0], expected);
+    }
+
+    #[test]
+    fn sum_epsilon_snaps_silence_to_equilibrium() {
+        let burst = |i: usize| {
+            ((i as f32 * 0.37).sin() * 0.9 + (i as f32 * 1.3).cos() * 0.05) * 1e-2
+        };
+        let mut rms = Rms::<[f32; 1]>::with_sum_epsilon(100, 1e-9);
+        assert_eq!(rms.sum_epsilon(), 1e-9);
+        for i in 0..1033 {
+            rms.next([burst(i)]);
+        }
+        for _ in 0..100 {
+            rms.next([0.0]);
+        }
+        assert_eq!(rms.next([0.0]), [0.0]);
+        assert_eq!(Rms::<[f32; 1]>::with_sum_epsilon(4, -1.0).sum_epsilon(), 0.0);
+    }
+
+    #[test]
+    fn parts_round_trip() {
+        let mut rms = Rms::<[f32; 2]>::new(8);
+        for i in 0..20 {
+            rms.next([i as f32 * 0.1, -0.3]);
+        }
+        let (window, sum) = rms.clone().into_parts();
+        let mut restored = Rms::from_parts(window, sum);
+        assert_eq!(restored.window_frames(), 8);
+        for i in 0..20 {
+            let x = [(i as f32).sin(), 0.2];
+            let (a, b) = (rms.next(x), restored.next(x));
+            assert!((a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn squares_are_ordered_oldest_first() {
+        let mut rms = Rms::<[f32; 1]>::new(5);
+        rms.next([0.5]);
+        rms.next([2.0]);
+        let squares: Vec<_> = rms.squares().cloned().collect();
+        assert_eq!(squares, vec![[0.0], [0.0], [0.0], [0.25], [4.0]]);
+    }
+
+    #[test]
+    fn reset_zeroes_the_window_without_resizing() {
+        let mut rms = Rms::<[f32; 2]>::with_shape(16, WindowShape::Hann);
+        for i in 0..40 {
+            rms.next([i as f32, 1.0]);
+        }
+        rms.reset();
+        assert!(rms.squares().all(|s| *s == [0.0, 0.0]));
+        assert_eq!(rms.window_frames(), 16);
+        assert_eq!(rms.mean_square(), [0.0, 0.0]);
+        assert_eq!(rms.shape(), WindowShape::Hann);
+    }
+
+    #[test]
+    fn hann_window_rises_more_smoothly_than_rectangular() {
+        let mut rect = Rms::<[f32; 1]>::new(100);
+        let mut hann = Rms::<[f32; 1]>::with_shape(100, WindowShape::Hann);
+        let (mut prev_rect, mut prev_hann, mut step_rect, mut step_hann) = (0.0, 0.0, 0.0, 0.0);
+        for _ in 0..300 {
+            let r = rect.next([1.0])[0];
+            let h = hann.next([1.0])[0];
+            step_rect = f32::max(step_rect, r - prev_rect);
+            step_hann = f32::max(step_hann, h - prev_hann);
+            prev_rect = r;
+            prev_hann = h;
+        }
+        assert!(step_hann < step_rect / 3.0, "{} {}", step_hann, step_rect);
+        assert!((prev_hann - 1.0).abs() < 1e-4 && (prev_rect - 1.0).abs() < 1e-4);
+
+        hann.set_window_frames(50);
+        for _ in 0..50 {
+            hann.next([0.5]);
+        }
+        assert!((hann.next([0.5])[0] - 0.5).abs() < 1e-4);
+        hann.set_shape(WindowShape::Rectangular);
+        assert!((hann.next([0.5])[0] - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn power_of_summed_busses_matches_the_combined_rms() {
+        let n = 64;
+        let (mut a, mut b) = (Rms::<[f32; 1]>::new(n), Rms::<[f32; 1]>::new(n));
+        let mut window: VecDeque<(f32, f32)> = VecDeque::new();
+        for i in 0..1000 {
+            let x = (i as f32 * 0.2).sin();
+            let y = 0.5 * (i as f32 * 0.2 + 0.3).sin();
+            let power = a.next_power([x])[0] + b.next_power([y])[0];
+            window.push_back((x, y));
+            if window.len() > n {
+                window.pop_front();
+            }
+            let sum: f32 = window.iter().map(|&(x, y)| x * x + y * y).sum();
+            let expected = (sum / n as f32).sqrt();
+            assert!((power.sqrt() - expected).abs() < 1e-5, "{} {}", power.sqrt(), expected);
+        }
+    }
+
+    #[test]
+    fn next_hop_matches_frame_by_frame_processing() {
+        let signal: Vec<[f32; 2]> = (0..100)
+            .map(|i| [(i as f32 * 0.3).sin(), (i % 7) as f32 * 0.1])
+            .collect();
+        for &shape in &[WindowShape::Rectangular, WindowShape::Hann] {
+            let mut frames = Rms::<[f32; 2]>::with_shape(16, shape);
+            let mut hops = frames.clone();
+            for hop in signal.chunks(8) {
+                let mut last = [0.0; 2];
+                for &frame in hop {
+                    last = frames.next(frame);
+                }
+                assert_eq!(hops.next_hop(hop), last);
+            }
+            assert_eq!(hops.next_hop(&[]), frames.mean_square().map(|s| s.sqrt()));
+        }
+    }
+
+    #[test]
+    fn array_rms_matches_rms() {
+        let mut array: ArrayRms<[f32; 2], 37> = Rms::with_array();
+        let mut rms = Rms::<[f32; 2]>::new(37);
+        assert_eq!(array.window_frames(), 37);
+        for i in 0..1000 {
+            let x = [(i as f32 * 0.07).sin(), if i % 300 < 150 { 0.5 } else { 0.0 }];
+            assert_eq!(array.next(x), rms.next(x), "{}", i);
+        }
+        assert!(array.squares().eq(rms.squares()));
+        array.reset();
+        assert_eq!(array, ArrayRms::default());
+
+        // The array backed window supports the same shapes and epsilon.
+        let mut array = Rms::<[f32; 1]>::with_array::<64>();
+        array.set_shape(WindowShape::Hann);
+        array.set_sum_epsilon(1e-9);
+        let mut rms = Rms::<[f32; 1]>::with_shape(64, WindowShape::Hann);
+        rms.set_sum_epsilon(1e-9);
+        for i in 0..500 {
+            let x = [if i < 200 { (i as f32 * 0.3).sin() * 1e-3 } else { 0.0 }];
+            assert_eq!(array.next(x), rms.next(x), "{}", i);
+        }
+        assert_eq!(array.mean_square(), [0.0]);
+    }
+
+    #[test]
+    fn exp_rms_approximates_a_windowed_rms() {
+        let mut exp = ExpRms::<[f64; 1]>::new(2000.0);
+        let mut rms = Rms::<[f64; 1]>::new(1000);
+        let (mut a, mut b) = ([0.0], [0.0]);
+        for i in 0..40_000 {
+            let x = [(i as f64 * 0.1).sin()];
+            a = exp.next(x);
+            b = rms.next(x);
+        }
+        assert!((a[0] - b[0]).abs() < 0.01, "{:?} {:?}", a, b);
+        assert!((exp.window_frames() - 2000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn retune_rescales_the_window_from_its_exact_length() {
+        let mut rms = Rms::<[f32; 1]>::new(10);
+        // Each ratio alone rounds back to ten frames, but together they add up to an eleventh.
+        for _ in 0..20 {
+            rms.retune(1.005);
+        }
+        assert_eq!(rms.window_frames(), 11);
+        let mut exp = ExpRms::<[f32; 1]>::new(100.0);
+        for _ in 0..1000 {
+            exp.retune(44_100.0 / 48_000.0);
+            exp.retune(48_000.0 / 44_100.0);
+        }
+        assert!((exp.window_frames() - 100.0).abs() < 1e-3, "{}", exp.window_frames());
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn next_simd_matches_next() {
+        let mut rms = Rms::<[f32; 19]>::new(64);
+        let mut simd = rms.clone();
+        for i in 0..1000 {
+            let mut x = [0.0; 19];
+            for (c, s) in x.iter_mut().enumerate() {
+                *s = ((i * (c + 1)) as f32 * 0.01).sin();
+            }
+            let (a, b) = (rms.next(x), simd.next_simd(x));
+            for c in 0..19 {
+                assert!((a[c] - b[c]).abs() < 1e-5);
+            }
+        }
+    }
 }
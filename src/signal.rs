@@ -0,0 +1,260 @@
+//! Adapters for lazily detecting the envelope of a `sample::Signal`.
+//!
+//! The primary type of interest in this module is the [**Envelope**](./struct.Envelope)
+//! iterator, produced via **EnvelopeDetector::envelope**. The
+//! [**EnvelopeZip**](./struct.EnvelopeZip) iterator, produced via
+//! **EnvelopeDetector::envelope_zip**, additionally yields each input frame alongside its
+//! envelope.
+//!
+//! The [**SignalEnvelope**](./trait.SignalEnvelope) trait allows for producing an **Envelope**
+//! from the signal side, i.e. `signal.envelope(detector).scale_amp(2.0)`.
+
+use {EnvelopeDetector, FullScale, Mode};
+use sample::{Frame, Signal};
+use std;
+
+
+/// An iterator that yields the envelope of each frame yielded by the signal `S`.
+///
+/// As **Envelope** is an `Iterator` yielding `Frame`s, it is also a `sample::Signal` (via the
+/// blanket implementation provided by `sample`) whose `Item` is the frame type `F`. This allows
+/// for composing it with the rest of the `sample::Signal` combinators, i.e. `scale_amp`,
+/// `add_amp` or `mul_hz`.
+#[derive(Clone)]
+pub struct Envelope<S, F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    signal: S,
+    detector: EnvelopeDetector<F, M>,
+}
+
+impl<S, F, M> std::fmt::Debug for Envelope<S, F, M>
+    where S: std::fmt::Debug,
+          F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Envelope {{ signal: {:?}, detector: {:?} }}", &self.signal, &self.detector)
+    }
+}
+
+
+impl<S, F, M> Envelope<S, F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+
+    /// Construct a new **Envelope** that detects the envelope of the given signal.
+    pub fn new(signal: S, detector: EnvelopeDetector<F, M>) -> Self {
+        Envelope {
+            signal,
+            detector,
+        }
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M> {
+        &mut self.detector
+    }
+
+    /// Consume the **Envelope** and return the signal and the **EnvelopeDetector**.
+    pub fn into_parts(self) -> (S, EnvelopeDetector<F, M>) {
+        let Envelope { signal, detector } = self;
+        (signal, detector)
+    }
+
+}
+
+impl<S, F, M> Iterator for Envelope<S, F, M>
+    where S: Iterator<Item=F>,
+          F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+    type Item = F;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.signal.next().map(|frame| self.detector.next(frame))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.signal.size_hint()
+    }
+}
+
+impl<S, F, M> ExactSizeIterator for Envelope<S, F, M>
+    where S: ExactSizeIterator<Item=F>,
+          F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.signal.len()
+    }
+}
+
+
+/// An iterator that yields each frame yielded by the signal `I` paired with its envelope, as
+/// `(input, envelope)`.
+///
+/// This is useful for inspecting the ballistics of a detector against the original signal.
+#[derive(Clone)]
+pub struct EnvelopeZip<I, F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+    signal: I,
+    detector: EnvelopeDetector<F, M>,
+}
+
+impl<I, F, M> std::fmt::Debug for EnvelopeZip<I, F, M>
+    where I: std::fmt::Debug,
+          F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "EnvelopeZip {{ signal: {:?}, detector: {:?} }}", &self.signal, &self.detector)
+    }
+}
+
+
+impl<I, F, M> EnvelopeZip<I, F, M>
+    where F: Frame,
+          M: Mode<F>,
+{
+
+    /// Construct a new **EnvelopeZip** that detects the envelope of the given signal.
+    pub fn new(signal: I, detector: EnvelopeDetector<F, M>) -> Self {
+        EnvelopeZip {
+            signal,
+            detector,
+        }
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M> {
+        &mut self.detector
+    }
+
+    /// Consume the **EnvelopeZip** and return the signal and the **EnvelopeDetector**.
+    pub fn into_parts(self) -> (I, EnvelopeDetector<F, M>) {
+        let EnvelopeZip { signal, detector } = self;
+        (signal, detector)
+    }
+
+}
+
+impl<I, F, M> Iterator for EnvelopeZip<I, F, M>
+    where I: Iterator<Item=F>,
+          F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+    type Item = (F, F);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.signal.next().map(|frame| (frame, self.detector.next(frame)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.signal.size_hint()
+    }
+}
+
+impl<I, F, M> ExactSizeIterator for EnvelopeZip<I, F, M>
+    where I: ExactSizeIterator<Item=F>,
+          F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.signal.len()
+    }
+}
+
+
+/// Extends all `sample::Signal`s with a method for lazily detecting their envelope.
+///
+/// This is implemented for all signals, and is the signal-side equivalent of
+/// **EnvelopeDetector::envelope**, allowing for declarative chains of `sample::Signal`
+/// combinators, i.e. `signal.envelope(detector).scale_amp(2.0)`.
+pub trait SignalEnvelope: Signal
+    where Self::Item: Frame,
+{
+    /// Return an **Envelope** that lazily yields the envelope of each frame yielded by `self`
+    /// as detected by the given **EnvelopeDetector**.
+    fn envelope<M>(self,
+                   detector: EnvelopeDetector<Self::Item, M>) -> Envelope<Self, Self::Item, M>
+        where M: Mode<Self::Item>,
+    {
+        Envelope::new(self, detector)
+    }
+}
+
+impl<S> SignalEnvelope for S
+    where S: Signal,
+          S::Item: Frame,
+{
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_signal_matches_next() {
+        let frames: Vec<[f32; 1]> = (0..20).map(|i| [(i as f32 * 0.3).sin()]).collect();
+        let mut detector = EnvelopeDetector::peak(1.0, 4.0);
+        let lazy: Vec<_> = detector.envelope(frames.iter().cloned()).collect();
+        let eager: Vec<_> = frames.iter().map(|&f| detector.next(f)).collect();
+        assert_eq!(lazy, eager);
+    }
+
+    #[test]
+    fn envelope_chains_with_signal_combinators() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(0.0, 0.0);
+        let frames = vec![[0.25f32], [-0.5], [0.125]];
+        let scaled: Vec<[f32; 1]> = frames.clone().into_iter()
+            .envelope(detector)
+            .scale_amp(2.0)
+            .collect();
+        assert_eq!(scaled, vec![[0.5], [1.0], [0.25]]);
+        let offsets = vec![[1.0f32]; 3].into_iter();
+        let summed: Vec<[f32; 1]> = detector.envelope(frames.into_iter())
+            .add_amp(offsets)
+            .collect();
+        assert_eq!(summed, vec![[1.25], [1.5], [1.125]]);
+    }
+
+    #[test]
+    fn envelope_zip_pairs_each_frame_with_its_envelope() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::peak(2.0, 20.0);
+        let mut reference = detector;
+        let pairs: Vec<_> = detector.envelope_zip((0..50).map(|i| [(i as f32 * 0.3).sin()]))
+            .collect();
+        assert_eq!(pairs.len(), 50);
+        for (frame, env) in pairs {
+            assert_eq!(env, reference.next(frame));
+        }
+    }
+}
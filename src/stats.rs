@@ -0,0 +1,40 @@
+//! Summary statistics of the envelope detected over a block of frames.
+//!
+//! The primary type of interest in this module is the [**EnvelopeStats**](./struct.EnvelopeStats)
+//! type, produced via **EnvelopeDetector::analyze**.
+
+use sample::Frame;
+use std;
+
+
+/// The per-channel maximum, minimum and mean of the envelope detected over a block of frames.
+#[derive(Copy, Clone)]
+pub struct EnvelopeStats<F>
+    where F: Frame,
+{
+    /// The greatest envelope value on each channel.
+    pub max: F,
+    /// The least envelope value on each channel.
+    pub min: F,
+    /// The mean envelope value on each channel.
+    pub mean: F::Float,
+}
+
+impl<F> std::fmt::Debug for EnvelopeStats<F>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "EnvelopeStats {{ max: {:?}, min: {:?}, mean: {:?} }}",
+               &self.max, &self.min, &self.mean)
+    }
+}
+
+impl<F> PartialEq for EnvelopeStats<F>
+    where F: Frame + PartialEq,
+          F::Float: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.max == other.max && self.min == other.min && self.mean == other.mean
+    }
+}
@@ -0,0 +1,242 @@
+//! An **EnvelopeDetector** wrapper that carries a sample rate so that it may be configured in
+//! milliseconds.
+//!
+//! The primary type of interest in this module is the
+//! [**TimedEnvelopeDetector**](./struct.TimedEnvelopeDetector).
+
+use {EnvelopeDetector, FullScale, Mode, Rms};
+use sample::{FloatSample, Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// An **EnvelopeDetector** along with the sample rate of the signal that it is detecting.
+///
+/// The sample rate is used to convert the attack, release and window times given in
+/// milliseconds to the frame counts expected by the inner **EnvelopeDetector**, whose gain type
+/// `G` it shares.
+///
+/// Note that changing the sample rate via `set_sample_hz` does *not* retune the existing attack,
+/// release or window lengths. These are only updated when their respective setter is next called,
+/// or for all at once via `retune`.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F: Serialize, F::Float: Serialize, \
+                                                       M: Serialize, G: Serialize",
+                                          deserialize = "F: Deserialize<'de>, \
+                                                         F::Float: Deserialize<'de>, \
+                                                         M: Deserialize<'de>, \
+                                                         G: Deserialize<'de>")))]
+pub struct TimedEnvelopeDetector<F, M, G = f32>
+    where F: Frame,
+          M: Mode<F>,
+          G: FloatSample,
+{
+    detector: EnvelopeDetector<F, M, G>,
+    sample_hz: f64,
+}
+
+impl<F, M, G> std::fmt::Debug for TimedEnvelopeDetector<F, M, G>
+    where F: Frame + std::fmt::Debug,
+          F::Float: std::fmt::Debug,
+          M: Mode<F> + std::fmt::Debug,
+          G: FloatSample + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "TimedEnvelopeDetector {{ detector: {:?}, sample_hz: {:?} }}",
+               &self.detector, &self.sample_hz)
+    }
+}
+
+
+impl<F> TimedEnvelopeDetector<F, Rms<F>>
+    where F: Frame,
+          F::Sample: FullScale,
+{
+
+    /// Set the duration of the **Rms** window in milliseconds.
+    pub fn set_window_ms(&mut self, ms: f64) {
+        self.detector.set_window_ms(ms, self.sample_hz);
+    }
+
+}
+
+impl<F, M, G> TimedEnvelopeDetector<F, M, G>
+    where F: Frame,
+          F::Sample: FullScale,
+          M: Mode<F>,
+          G: FloatSample,
+{
+
+    /// Construct a new **TimedEnvelopeDetector** from the given detector and sample rate.
+    pub fn new(detector: EnvelopeDetector<F, M, G>, sample_hz: f64) -> Self {
+        TimedEnvelopeDetector {
+            detector,
+            sample_hz,
+        }
+    }
+
+    /// The sample rate used to convert milliseconds to frames.
+    pub fn sample_hz(&self) -> f64 {
+        self.sample_hz
+    }
+
+    /// Set the sample rate used to convert milliseconds to frames.
+    ///
+    /// This does not retune the current attack, release or window lengths.
+    pub fn set_sample_hz(&mut self, sample_hz: f64) {
+        self.sample_hz = sample_hz;
+    }
+
+    /// Change the sample rate while retaining the attack, release and window times in seconds,
+    /// i.e. when the effective sample rate changes from block to block under varispeed.
+    ///
+    /// The attack and release (including any per-channel and slew-limited times) and any
+    /// durations held by the **Mode** (see **Mode::retune**) are rescaled by the ratio of the
+    /// given sample rate to the current one, keeping the perceived ballistics constant. Each
+    /// duration is rescaled from its exact length, so whole frame durations such as the **Rms**
+    /// window are only rounded to the nearest frame once rescaled. Repeated calls therefore
+    /// neither lose small changes in rate nor drift.
+    ///
+    /// If either the current or the given sample rate is not positive and finite, only the sample
+    /// rate is updated.
+    pub fn retune(&mut self, sample_hz: f64) {
+        let ratio = sample_hz / self.sample_hz;
+        if ratio > 0.0 && ratio.is_finite() {
+            self.detector.retune(ratio);
+        }
+        self.sample_hz = sample_hz;
+    }
+
+    /// Set the attack time in milliseconds.
+    pub fn set_attack_ms(&mut self, ms: f64) {
+        let frames = ::ms_frames(ms, self.sample_hz);
+        self.detector.set_attack_frames_f64(frames);
+    }
+
+    /// Set the release time in milliseconds.
+    pub fn set_release_ms(&mut self, ms: f64) {
+        let frames = ::ms_frames(ms, self.sample_hz);
+        self.detector.set_release_frames_f64(frames);
+    }
+
+    /// A reference to the inner **EnvelopeDetector**.
+    pub fn detector(&self) -> &EnvelopeDetector<F, M, G> {
+        &self.detector
+    }
+
+    /// A mutable reference to the inner **EnvelopeDetector**.
+    pub fn detector_mut(&mut self) -> &mut EnvelopeDetector<F, M, G> {
+        &mut self.detector
+    }
+
+    /// Consume the **TimedEnvelopeDetector** and return the inner **EnvelopeDetector**.
+    pub fn into_detector(self) -> EnvelopeDetector<F, M, G> {
+        self.detector
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope frame.
+    ///
+    /// See **EnvelopeDetector::next**.
+    pub fn next(&mut self, frame: F) -> F {
+        self.detector.next(frame)
+    }
+
+    /// Given the next input signal frame, detect and return the next envelope average across each
+    /// channel for the frame.
+    ///
+    /// See **EnvelopeDetector::next_avg**.
+    pub fn next_avg(&mut self, frame: F) -> <F::Sample as Sample>::Float {
+        self.detector.next_avg(frame)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PeakHold;
+
+    #[test]
+    fn millisecond_setters_convert_at_the_sample_rate() {
+        let detector = EnvelopeDetector::<[f32; 1], _>::rms(1, 1.0, 1.0);
+        let mut timed = TimedEnvelopeDetector::new(detector, 44_100.0);
+        timed.set_attack_ms(1.0);
+        timed.set_release_ms(2.0);
+        timed.set_window_ms(10.0);
+        assert!((timed.detector().attack_frames() - 44.1).abs() < 1e-3);
+        assert!((timed.detector().release_frames() - 88.2).abs() < 1e-2);
+        assert_eq!(timed.detector().mode().window_frames(), 441);
+    }
+
+    #[test]
+    fn millisecond_setters_keep_the_frames_at_f64_precision() {
+        let detector = EnvelopeDetector::<[f64; 1], _>::peak(0.0, 0.0).with_gain_type::<f64>();
+        let mut timed = TimedEnvelopeDetector::new(detector, 44_100.0);
+        timed.set_release_ms(0.1);
+        // 4.41 frames, which is not representable as an `f32`.
+        let gain = (-1.0 / (0.1f64 * 44_100.0 / 1_000.0)).exp();
+        timed.detector_mut().reset_to([1.0]);
+        assert_eq!(timed.next([0.0]), [gain]);
+        timed.set_attack_ms(0.1);
+        timed.detector_mut().reset_to([0.0]);
+        assert_eq!(timed.next([1.0]), [1.0 - gain]);
+    }
+
+    #[test]
+    fn retune_keeps_the_times_in_seconds() {
+        let detector = EnvelopeDetector::<[f32; 2], _>::rms(1, 1.0, 1.0);
+        let mut timed = TimedEnvelopeDetector::new(detector, 48_000.0);
+        timed.set_attack_ms(10.0);
+        timed.set_release_ms(100.0);
+        timed.set_window_ms(5.0);
+        timed.detector_mut().set_release_slew_db_per_sec(20.0, 48_000.0);
+        timed.retune(24_000.0);
+        assert_eq!(timed.sample_hz(), 24_000.0);
+        let detector = timed.detector();
+        assert!((detector.attack_frames() - 240.0).abs() < 1e-3, "{}", detector.attack_frames());
+        assert!((detector.release_frames() - 2400.0).abs() < 1e-2);
+        assert_eq!(detector.mode().window_frames(), 120);
+        let db_per_frame = detector.release_slew_db_per_frame().unwrap();
+        assert!((db_per_frame - 20.0 / 24_000.0).abs() < 1e-7, "{}", db_per_frame);
+
+        let per_channel = EnvelopeDetector::with_frames_per_channel(PeakHold::<[f32; 2]>::new(100),
+                                                                    [10.0, 20.0],
+                                                                    [100.0, 200.0]);
+        let mut timed = TimedEnvelopeDetector::new(per_channel, 44_100.0);
+        timed.retune(88_200.0);
+        let detector = timed.detector();
+        assert_eq!((detector.channel_attack_frames(0), detector.channel_attack_frames(1)),
+                   (20.0, 40.0));
+        assert_eq!(detector.mode().hold_frames(), 200);
+
+        // Invalid rates update the sample rate only.
+        timed.retune(0.0);
+        timed.retune(44_100.0);
+        assert_eq!(timed.sample_hz(), 44_100.0);
+        assert_eq!(timed.detector().mode().hold_frames(), 200);
+    }
+
+    #[test]
+    fn repeated_retune_does_not_drift() {
+        let mut detector = EnvelopeDetector::<[f32; 2], _>::peak_hold(100, 10.0, 1000.0);
+        detector.set_attack_frames_per_channel([3.0, 7.0]);
+        let mut timed = TimedEnvelopeDetector::new(detector, 48_000.0);
+        for _ in 0..1000 {
+            timed.retune(44_100.0);
+            timed.retune(48_000.0);
+        }
+        let detector = timed.detector();
+        assert_eq!(detector.mode().hold_frames(), 100);
+        assert_eq!(detector.release_frames(), 1000.0);
+        assert_eq!(detector.channel_attack_frames(1), 7.0);
+
+        // A series of small changes in rate accumulates rather than rounding away.
+        for i in 1..11 {
+            timed.retune(48_000.0 * (1.0 + 0.001 * i as f64));
+        }
+        assert_eq!(timed.detector().mode().hold_frames(), 101);
+    }
+}
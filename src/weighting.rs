@@ -0,0 +1,339 @@
+//! Frequency weighting filters applied to a signal prior to level detection.
+//!
+//! The primary types of interest in this module are the [**KWeight**](./struct.KWeight) filter
+//! for loudness measurement and the [**AWeight**](./struct.AWeight) filter for perceptual
+//! (i.e. environmental noise) level measurement.
+
+use sample::{Frame, Sample};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std;
+
+
+/// The K-weighting pre-filter described by ITU-R BS.1770 for loudness measurement.
+///
+/// The filter is made up of two biquad stages: a high-shelf that models the acoustic effect of
+/// the head, followed by a high-pass (the "RLB" weighting curve). The coefficients are re-derived
+/// for the given sample rate via the bilinear transform, reproducing the published coefficients
+/// at 48 kHz.
+///
+/// The weighted output is intended to be fed to an **Rms** or an **EnvelopeDetector**, i.e.
+/// `rms.next(k_weight.next(frame))`.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub struct KWeight<F>
+    where F: Frame,
+{
+    shelf: Biquad<F>,
+    high_pass: Biquad<F>,
+}
+
+/// The standard A-weighting curve (IEC 61672) for perceptual level measurement.
+///
+/// The analog A-weighting transfer function is mapped to a sixth-order IIR filter (a cascade of
+/// three biquads) via the bilinear transform and normalised to unity gain at 1 kHz. Frequency
+/// warping is not pre-compensated, so the poles remain stable at any sample rate, including
+/// those whose Nyquist frequency falls below the 12.2 kHz pole of the curve. The response above
+/// roughly a quarter of the sample rate rolls off more steeply than the published curve as a
+/// result.
+///
+/// The weighted output is intended to be fed to an **Rms** or an **EnvelopeDetector**, i.e.
+/// `rms.next(a_weight.next(frame))`.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub struct AWeight<F>
+    where F: Frame,
+{
+    stages: [Biquad<F>; 3],
+}
+
+/// A single transposed direct form II biquad stage with state for each channel.
+///
+/// This is shared with the crossover filters of the **MultibandEnvelopeDetector**.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "F::Float: Serialize",
+                                          deserialize = "F::Float: Deserialize<'de>")))]
+pub(crate) struct Biquad<F>
+    where F: Frame,
+{
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: F::Float,
+    z2: F::Float,
+}
+
+impl<F> std::fmt::Debug for KWeight<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "KWeight {{ shelf: {:?}, high_pass: {:?} }}", &self.shelf, &self.high_pass)
+    }
+}
+
+impl<F> std::fmt::Debug for AWeight<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "AWeight {{ stages: {:?} }}", &self.stages)
+    }
+}
+
+impl<F> std::fmt::Debug for Biquad<F>
+    where F: Frame,
+          F::Float: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "Biquad {{ b0: {:?}, b1: {:?}, b2: {:?}, a1: {:?}, a2: {:?}, z1: {:?}, \
+                   z2: {:?} }}",
+               &self.b0, &self.b1, &self.b2, &self.a1, &self.a2, &self.z1, &self.z2)
+    }
+}
+
+
+impl<F> KWeight<F>
+    where F: Frame,
+{
+
+    /// Construct a new **KWeight** filter for a signal at the given sample rate.
+    pub fn new(sample_hz: f64) -> Self {
+        KWeight {
+            shelf: Biquad::k_shelf(sample_hz),
+            high_pass: Biquad::k_high_pass(sample_hz),
+        }
+    }
+
+    /// Clear the state of both filter stages.
+    pub fn reset(&mut self) {
+        self.shelf.reset();
+        self.high_pass.reset();
+    }
+
+    /// Given the next input signal frame, return the next K-weighted frame.
+    pub fn next(&mut self, frame: F) -> F::Float {
+        let shelved = self.shelf.next(frame.to_float_frame());
+        self.high_pass.next(shelved)
+    }
+
+}
+
+impl<F> AWeight<F>
+    where F: Frame,
+{
+
+    /// Construct a new **AWeight** filter for a signal at the given sample rate.
+    pub fn new(sample_hz: f64) -> Self {
+        const F1: f64 = 20.598997;
+        const F2: f64 = 107.65265;
+        const F3: f64 = 737.86223;
+        const F4: f64 = 12194.217;
+        const TAU: f64 = 2.0 * std::f64::consts::PI;
+        let high_pass = |hz: f64| first_order_high_pass(TAU * hz, sample_hz);
+        let low_pass = |hz: f64| first_order_low_pass(TAU * hz, sample_hz);
+        let mut stages = [
+            Biquad::from_first_order(high_pass(F1), high_pass(F1)),
+            Biquad::from_first_order(high_pass(F2), high_pass(F3)),
+            Biquad::from_first_order(low_pass(F4), low_pass(F4)),
+        ];
+        let gain_1khz = stages.iter().fold(1.0, |g, stage| g * stage.magnitude(1_000.0, sample_hz));
+        stages[0].scale(1.0 / gain_1khz);
+        AWeight {
+            stages,
+        }
+    }
+
+    /// Clear the state of all filter stages.
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+
+    /// Given the next input signal frame, return the next A-weighted frame.
+    pub fn next(&mut self, frame: F) -> F::Float {
+        self.stages.iter_mut().fold(frame.to_float_frame(), |x, stage| stage.next(x))
+    }
+
+}
+
+impl<F> Biquad<F>
+    where F: Frame,
+{
+
+    /// Construct a biquad from normalised coefficients (where `a0` is `1.0`).
+    fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Biquad {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            z1: Frame::equilibrium(),
+            z2: Frame::equilibrium(),
+        }
+    }
+
+    /// Construct a biquad from the product of two first-order sections, each given as its
+    /// `([b0, b1], [a0, a1])` coefficients.
+    fn from_first_order(first: ([f64; 2], [f64; 2]), second: ([f64; 2], [f64; 2])) -> Self {
+        let ((n1, d1), (n2, d2)) = (first, second);
+        let a0 = d1[0] * d2[0];
+        Biquad::new(n1[0] * n2[0] / a0,
+                    (n1[0] * n2[1] + n1[1] * n2[0]) / a0,
+                    n1[1] * n2[1] / a0,
+                    (d1[0] * d2[1] + d1[1] * d2[0]) / a0,
+                    d1[1] * d2[1] / a0)
+    }
+
+    /// The K-weighting high-shelf stage.
+    fn k_shelf(sample_hz: f64) -> Self {
+        const F0: f64 = 1681.974450955533;
+        const GAIN_DB: f64 = 3.999843853973347;
+        const Q: f64 = 0.7071752369554196;
+        let k = (std::f64::consts::PI * F0 / sample_hz).tan();
+        let vh = 10.0_f64.powf(GAIN_DB / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / Q + k * k;
+        Biquad::new((vh + vb * k / Q + k * k) / a0,
+                    2.0 * (k * k - vh) / a0,
+                    (vh - vb * k / Q + k * k) / a0,
+                    2.0 * (k * k - 1.0) / a0,
+                    (1.0 - k / Q + k * k) / a0)
+    }
+
+    /// The K-weighting high-pass stage.
+    fn k_high_pass(sample_hz: f64) -> Self {
+        const F0: f64 = 38.13547087602444;
+        const Q: f64 = 0.5003270373238773;
+        let k = (std::f64::consts::PI * F0 / sample_hz).tan();
+        let a0 = 1.0 + k / Q + k * k;
+        Biquad::new(1.0,
+                    -2.0,
+                    1.0,
+                    2.0 * (k * k - 1.0) / a0,
+                    (1.0 - k / Q + k * k) / a0)
+    }
+
+    /// A second-order Butterworth low-pass stage with a cutoff at the given frequency.
+    pub(crate) fn butterworth_low_pass(hz: f64, sample_hz: f64) -> Self {
+        let (k, a0, a1, a2) = butterworth_poles(hz, sample_hz);
+        Biquad::new(k * k / a0, 2.0 * k * k / a0, k * k / a0, a1, a2)
+    }
+
+    /// A second-order Butterworth high-pass stage with a cutoff at the given frequency.
+    pub(crate) fn butterworth_high_pass(hz: f64, sample_hz: f64) -> Self {
+        let (_, a0, a1, a2) = butterworth_poles(hz, sample_hz);
+        Biquad::new(1.0 / a0, -2.0 / a0, 1.0 / a0, a1, a2)
+    }
+
+    /// Scale the gain of the biquad by the given amount.
+    fn scale(&mut self, gain: f64) {
+        self.b0 *= gain;
+        self.b1 *= gain;
+        self.b2 *= gain;
+    }
+
+    /// The magnitude of the biquad's frequency response at the given frequency.
+    fn magnitude(&self, hz: f64, sample_hz: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * hz / sample_hz;
+        let (cos, cos2) = (w.cos(), (2.0 * w).cos());
+        let (sin, sin2) = (w.sin(), (2.0 * w).sin());
+        let num_re = self.b0 + self.b1 * cos + self.b2 * cos2;
+        let num_im = self.b1 * sin + self.b2 * sin2;
+        let den_re = 1.0 + self.a1 * cos + self.a2 * cos2;
+        let den_im = self.a1 * sin + self.a2 * sin2;
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.z1 = Frame::equilibrium();
+        self.z2 = Frame::equilibrium();
+    }
+
+    pub(crate) fn next(&mut self, x: F::Float) -> F::Float {
+        let Biquad { b0, b1, b2, a1, a2, z1, z2 } = *self;
+        let y = x.scale_amp(b0.to_sample()).add_amp(z1);
+        self.z1 = x.scale_amp(b1.to_sample())
+            .add_amp(y.scale_amp((-a1).to_sample()))
+            .add_amp(z2);
+        self.z2 = x.scale_amp(b2.to_sample())
+            .add_amp(y.scale_amp((-a2).to_sample()));
+        y
+    }
+
+}
+
+/// The bilinear transform of the analog high-pass section `s / (s + w)`.
+fn first_order_high_pass(w: f64, sample_hz: f64) -> ([f64; 2], [f64; 2]) {
+    let c = 2.0 * sample_hz;
+    ([c, -c], [c + w, w - c])
+}
+
+/// The bilinear transform of the analog low-pass section `1 / (s + w)`.
+fn first_order_low_pass(w: f64, sample_hz: f64) -> ([f64; 2], [f64; 2]) {
+    let c = 2.0 * sample_hz;
+    ([1.0, 1.0], [c + w, w - c])
+}
+
+/// The prewarped bilinear transform of a second-order Butterworth section with the given cutoff,
+/// returning `k` along with the `a0` used for normalisation and the normalised `a1` and `a2`.
+fn butterworth_poles(hz: f64, sample_hz: f64) -> (f64, f64, f64, f64) {
+    const Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+    let k = (std::f64::consts::PI * hz / sample_hz).tan();
+    let a0 = 1.0 + k / Q + k * k;
+    (k, a0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The gain in decibels applied by the filter to a sine at the given frequency.
+    fn response_db<W>(mut filter: W, hz: f64, sample_hz: f64) -> f64
+        where W: FnMut([f64; 1]) -> [f64; 1],
+    {
+        let n_frames = (sample_hz * 2.0) as usize;
+        let mut sum = 0.0;
+        // Skip the first half so that the filter has settled.
+        for i in 0..n_frames {
+            let x = (2.0 * std::f64::consts::PI * hz * i as f64 / sample_hz).sin();
+            let y = filter([x])[0];
+            if i >= n_frames / 2 {
+                sum += y * y;
+            }
+        }
+        let mean_square = sum / (n_frames - n_frames / 2) as f64;
+        10.0 * (mean_square / 0.5).log10()
+    }
+
+    #[test]
+    fn k_weighting_matches_the_bs_1770_curve() {
+        for &(hz, expected) in &[(997.0, 0.69), (100.0, -1.15), (10_000.0, 4.0), (20.0, -13.3)] {
+            let mut k = KWeight::<[f64; 1]>::new(48_000.0);
+            let db = response_db(|x| k.next(x), hz, 48_000.0);
+            assert!((db - expected).abs() < 0.6, "{} {}", hz, db);
+        }
+    }
+
+    #[test]
+    fn a_weighting_is_unity_at_1khz() {
+        for &sample_hz in &[8000.0, 48_000.0] {
+            let mut a = AWeight::<[f64; 1]>::new(sample_hz);
+            let db = response_db(|x| a.next(x), 1000.0, sample_hz);
+            assert!(db.abs() < 0.05, "{} {}", sample_hz, db);
+        }
+        let mut a = AWeight::<[f64; 1]>::new(48_000.0);
+        let db = response_db(|x| a.next(x), 100.0, 48_000.0);
+        assert!((db + 19.1).abs() < 0.3, "{}", db);
+    }
+}